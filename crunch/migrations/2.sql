@@ -0,0 +1 @@
+ALTER TABLE combos ADD COLUMN IF NOT EXISTS rerank_score FLOAT;
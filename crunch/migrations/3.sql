@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS unmatched (
+    contemporary_uri TEXT NOT NULL PRIMARY KEY,
+    best_score FLOAT,
+    time_checked TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
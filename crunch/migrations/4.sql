@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS canonical_uri (
+    uri TEXT NOT NULL PRIMARY KEY,
+    canonical_uri TEXT NOT NULL,
+    time_added TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS canonical_uri_canonical ON canonical_uri (canonical_uri);
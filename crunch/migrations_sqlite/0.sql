@@ -0,0 +1,89 @@
+CREATE TABLE IF NOT EXISTS scraped_headline (
+    uri TEXT NOT NULL PRIMARY KEY,
+    main TEXT NOT NULL,
+    kicker TEXT,
+    content_kicker TEXT,
+    print_headline TEXT NOT NULL,
+    name TEXT,
+    seo TEXT,
+    sub TEXT
+);
+
+CREATE TABLE IF NOT EXISTS scraped_multimedia (
+    uri TEXT NOT NULL PRIMARY KEY,
+    rank INTEGER NOT NULL,
+    subtype TEXT NOT NULL,
+    caption TEXT,
+    credit TEXT,
+    type_ TEXT NOT NULL,
+    url TEXT NOT NULL,
+    height INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    crop_name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS scraped_article (
+    uri TEXT NOT NULL PRIMARY KEY,
+    web_url TEXT NOT NULL,
+    snippet TEXT NOT NULL,
+    print_page TEXT,
+    print_section TEXT,
+    source TEXT NOT NULL,
+    pub_date TEXT NOT NULL,
+    document_type TEXT NOT NULL,
+    news_desk TEXT NOT NULL,
+    section_name TEXT NOT NULL,
+    type_of_material TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS contemporary_multimedia (
+    uri TEXT NOT NULL PRIMARY KEY,
+    url TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    format TEXT,
+    type_ TEXT NOT NULL,
+    subtype TEXT,
+    caption TEXT
+);
+
+CREATE TABLE IF NOT EXISTS contemporary_article (
+    uri TEXT NOT NULL PRIMARY KEY,
+    url TEXT NOT NULL,
+    yy INTEGER NOT NULL,
+    mm INTEGER NOT NULL,
+    dd INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    abstract TEXT NOT NULL,
+    section TEXT NOT NULL,
+    subsection TEXT NOT NULL,
+    item_type TEXT NOT NULL,
+    kicker TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS current (
+    uri TEXT NOT NULL PRIMARY KEY,
+    rank INTEGER NOT NULL,
+    time_added TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS combos (
+    contemporary_uri TEXT NOT NULL,
+    past_uri TEXT NOT NULL,
+    score REAL NOT NULL,
+    rerank_score REAL,
+    PRIMARY KEY (contemporary_uri, past_uri)
+);
+
+CREATE TABLE IF NOT EXISTS unmatched (
+    contemporary_uri TEXT NOT NULL PRIMARY KEY,
+    best_score REAL,
+    time_checked TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS canonical_uri (
+    uri TEXT NOT NULL PRIMARY KEY,
+    canonical_uri TEXT NOT NULL,
+    time_added TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS canonical_uri_canonical ON canonical_uri (canonical_uri);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS promoted (
+    uri TEXT NOT NULL PRIMARY KEY,
+    from_archive BOOLEAN NOT NULL,
+    time_promoted TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS contemporary_source (
+    uri TEXT NOT NULL PRIMARY KEY,
+    source TEXT NOT NULL
+);
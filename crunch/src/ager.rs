@@ -0,0 +1,202 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+
+use chrono::{Datelike, Months, NaiveDate};
+use cyclicism::{
+    archive::{ArchiveClient, BACKOFF, MAX_RETRIES_PER_MONTH},
+    compress::{find_scrape, Compression},
+    embed::{embed_async, CachedEmbedder, Embedder, FastEmbedder},
+    flat::FlatStore,
+    junk::{JunkFilter, JunkRules},
+    manifest::Manifest,
+    mydrant::{break_article_for_mydrant, BedSource, Collection, DetailedEmbedding},
+    nyt::{ScrapedArticle, ScrapedDocs, StoredContemporary, NYT_SOURCE_NAME},
+    repo::{get_repository, Repository},
+    store::VectorStore,
+};
+use fastembed::EmbeddingModel;
+use qdrant_client::{qdrant::Distance, Qdrant};
+
+/// NYT articles older than this get promoted even if the Archive API never has them. Other
+/// sources have no archive to wait for, so theirs go as soon as their month is over.
+const HORIZON_YEARS: u32 = 5;
+/// Whether to fetch a month from the Archive API when it's over and we don't have it locally
+const FETCH_ARCHIVE: bool = true;
+/// Should match the embeddor, since this writes into the same collection
+const BED_SOURCE: BedSource = BedSource::HeadlineMain;
+const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
+const DISTANCE: Distance = Distance::Cosine;
+/// Set to `Some(path)` to promote into a `FlatStore` file instead of qdrant
+const LOCAL_STORE: Option<&str> = None;
+const BED_CACHE_DIR: &str = "bed_cache";
+const JUNK_RULES: JunkRules = JunkRules::DEFAULT;
+/// How fetched months get stored, like the scraper's
+const COMPRESSION: Compression = Compression::Plain;
+const CHUNK_SIZE: usize = 64;
+
+/// The archive versions of whichever of `uris` are in this month's scrape
+async fn archive_versions(
+    month: NaiveDate,
+    uris: &HashSet<&str>,
+) -> anyhow::Result<HashMap<String, ScrapedArticle>> {
+    let mut found = HashMap::new();
+    let mut docs = ScrapedDocs::open(month)?;
+    while let Some(article) = docs.next().await {
+        let article = article?;
+        if uris.contains(&article.uri.as_str()) {
+            found.insert(article.uri.clone(), article);
+        }
+    }
+    Ok(found)
+}
+
+/// Moves articles into the archive tables and the collection, then marks them promoted
+async fn promote(
+    mut promotions: Vec<(ScrapedArticle, bool)>,
+    repo: &dyn Repository,
+    store: &dyn VectorStore,
+    embedder: Arc<dyn Embedder>,
+    junk: &JunkFilter,
+) -> anyhow::Result<()> {
+    while !promotions.is_empty() {
+        let chunk = promotions
+            .drain(0..CHUNK_SIZE.min(promotions.len()))
+            .collect::<Vec<_>>();
+        let mut marks = vec![];
+        let mut documents = vec![];
+        let mut broad_details = vec![];
+        for (article, from_archive) in chunk {
+            repo.upsert_scraped(&article).await?;
+            marks.push((article.uri.clone(), from_archive));
+            // Junk still counts as promoted, it just doesn't get searched
+            if let Some((uuid, text, info)) = break_article_for_mydrant(article, BED_SOURCE, junk) {
+                documents.push(text);
+                broad_details.push((uuid, info));
+            }
+        }
+        let beds = embed_async(embedder.clone(), documents).await?;
+        let data = beds
+            .into_iter()
+            .zip(broad_details)
+            .map(|(bed, (uuid, info))| DetailedEmbedding { uuid, bed, info })
+            .collect::<Vec<_>>();
+        store.upsert(data).await?;
+        for (uri, from_archive) in marks {
+            repo.mark_promoted(&uri, from_archive).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let repo = get_repository(2).await?;
+    repo.apply_migrations().await?;
+    let pending = repo.unpromoted_contemporary().await?;
+    let mut by_month: BTreeMap<NaiveDate, Vec<StoredContemporary>> = BTreeMap::new();
+    for stored in pending {
+        by_month
+            .entry(stored.date.with_day(1).unwrap())
+            .or_default()
+            .push(stored);
+    }
+
+    let archive = match FETCH_ARCHIVE {
+        true => Some(ArchiveClient::from_env()?),
+        false => None,
+    };
+    std::fs::create_dir_all("scrapes")?;
+    let mut manifest = Manifest::load()?;
+
+    let today = chrono::Utc::now().date_naive();
+    let horizon = today - Months::new(12 * HORIZON_YEARS);
+    let embedder: Arc<dyn Embedder> = Arc::new(CachedEmbedder::try_new(
+        Box::new(FastEmbedder::try_new(BED_MODEL)?),
+        Path::new(BED_CACHE_DIR),
+    )?);
+    let junk = JunkFilter::try_new(JUNK_RULES)?;
+    let store: Box<dyn VectorStore> = match LOCAL_STORE {
+        Some(path) => Box::new(FlatStore::open(
+            Path::new(path),
+            BED_SOURCE,
+            embedder.as_ref(),
+            DISTANCE,
+        )?),
+        None => {
+            let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+            let collection = Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant);
+            collection.verify().await?;
+            Box::new(collection)
+        }
+    };
+
+    let mut num_from_archive = 0;
+    let mut num_from_stored = 0;
+    let mut num_waiting = 0;
+    for (month, articles) in by_month {
+        // Nothing's final until the month is over
+        if month + Months::new(1) > today {
+            num_waiting += articles.len();
+            continue;
+        }
+        let nyt_uris = articles
+            .iter()
+            .filter(|a| a.source == NYT_SOURCE_NAME)
+            .map(|a| a.uri.as_str())
+            .collect::<HashSet<_>>();
+        if let Some(archive) = archive.as_ref().filter(|_| !nyt_uris.is_empty()) {
+            let fetched = archive
+                .fetch_month(
+                    month,
+                    COMPRESSION,
+                    &mut manifest,
+                    BACKOFF,
+                    MAX_RETRIES_PER_MONTH,
+                )
+                .await;
+            if let Err(e) = fetched {
+                println!("Couldn't fetch from the Archive API: {e}");
+            }
+        }
+        let mut archived = match find_scrape(month) {
+            Some(_) if !nyt_uris.is_empty() => archive_versions(month, &nyt_uris).await?,
+            _ => HashMap::new(),
+        };
+
+        let mut promotions = vec![];
+        for stored in articles {
+            match archived.remove(&stored.uri) {
+                Some(article) => promotions.push((article, true)),
+                None if stored.source != NYT_SOURCE_NAME || stored.date < horizon => {
+                    promotions.push((stored.to_scraped(), false))
+                }
+                None => num_waiting += 1,
+            }
+        }
+        num_from_archive += promotions
+            .iter()
+            .filter(|(_, from_archive)| *from_archive)
+            .count();
+        num_from_stored += promotions
+            .iter()
+            .filter(|(_, from_archive)| !from_archive)
+            .count();
+        promote(
+            promotions,
+            repo.as_ref(),
+            store.as_ref(),
+            embedder.clone(),
+            &junk,
+        )
+        .await?;
+        println!("Finished {}/{}", month.year(), month.month());
+    }
+    println!(
+        "Promoted {num_from_archive} from the Archive API and {num_from_stored} as stored (other sources, or NYT past the {HORIZON_YEARS} year horizon), {num_waiting} still waiting"
+    );
+    junk.report();
+    Ok(())
+}
@@ -1,8 +1,5 @@
 use axum::{routing::get, Router};
-use cyclicism::{
-    nyt::FrontendArticle,
-    pg::{apply_migrations, get_pg_pool},
-};
+use cyclicism::{nyt::FrontendArticle, repo::get_repository};
 use tracing::info;
 
 mod combos_on_date;
@@ -17,13 +14,13 @@ async fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     // Database
-    let pg_pool = get_pg_pool(6).await?;
-    apply_migrations(&pg_pool).await?;
+    let repo = get_repository(6).await?;
+    repo.apply_migrations().await?;
 
     // Routing
     let app = Router::new()
         .route("/combos_on_date", get(get_combos_on_date))
-        .with_state(pg_pool);
+        .with_state(repo);
 
     // Run it
     info!(
@@ -57,4 +54,6 @@ struct PastArticle {
 struct Combo {
     contemporary: ContemporaryArticle,
     past: Vec<PastArticle>,
+    /// True when nothing in the archive was close enough to show. `past` will be empty.
+    no_historical_parallel: bool,
 }
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate};
+use reqwest::{Client, StatusCode};
+
+use crate::{
+    compress::{find_scrape, Compression},
+    manifest::{write_atomic, Manifest, ManifestEntry},
+    nyt::{nyt_api_key, nyt_base_url},
+    retry::{retry_after, Backoff},
+};
+
+/// How many times the scraper and the ager retry one month before giving up
+pub const MAX_RETRIES_PER_MONTH: u32 = 8;
+/// Starts at 15 seconds (about what the Archive API's rate limit allows) and doubles on each
+/// retry, up to 10 minutes
+pub const BACKOFF: Backoff = Backoff {
+    base_secs: 15,
+    max_secs: 600,
+};
+
+/// How one try at a month went
+#[derive(Debug)]
+pub enum MonthStatus {
+    AlreadyExists,
+    Downloaded,
+    /// Something that might work if we wait (rate limits, server errors, truncated bodies...)
+    Retry {
+        reason: String,
+        /// What the server asked us to wait, if it said
+        after: Option<Duration>,
+    },
+    /// Something retrying won't fix, like a bad API key
+    Fatal(String),
+}
+
+/// The Archive API, which gives a whole month per request
+pub struct ArchiveClient {
+    client: Client,
+    base_url: String,
+    api_key: String,
+}
+impl ArchiveClient {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// Uses `NYT_BASE_URL` and `NYT_API_KEY`
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self::new(&nyt_base_url(), &nyt_api_key()?))
+    }
+
+    /// Gets the json and writes it to the file, but only if it's actually a month of articles.
+    /// Does NOT sleep.
+    pub async fn handle_month(
+        &self,
+        date: NaiveDate,
+        compression: Compression,
+        manifest: &mut Manifest,
+    ) -> MonthStatus {
+        if find_scrape(date).is_some() {
+            return MonthStatus::AlreadyExists;
+        }
+        let resp = match self
+            .client
+            .get(format!(
+                "{}/svc/archive/v1/{}/{}.json",
+                self.base_url,
+                date.year_ce().1,
+                date.month0() + 1,
+            ))
+            .query(&[("api-key", &self.api_key)])
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                return MonthStatus::Retry {
+                    reason: format!("request failed: {e}"),
+                    after: None,
+                }
+            }
+        };
+
+        let status = resp.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return MonthStatus::Retry {
+                reason: format!("got {status}"),
+                after: retry_after(&resp),
+            };
+        }
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return MonthStatus::Fatal(format!(
+                "got {status}, is NYT_API_KEY right and does it have the Archive API enabled?"
+            ));
+        }
+        let body = match resp.bytes().await {
+            Ok(body) => body,
+            Err(e) => {
+                return MonthStatus::Retry {
+                    reason: format!("couldn't read body: {e}"),
+                    after: None,
+                }
+            }
+        };
+        if !status.is_success() {
+            return MonthStatus::Fatal(format!(
+                "got {status}: {}",
+                String::from_utf8_lossy(&body)
+                    .chars()
+                    .take(200)
+                    .collect::<String>()
+            ));
+        }
+
+        // Anything we write has to load later, so check now rather than in the cleaner
+        let entry = match ManifestEntry::from_body(&body, Some(status.as_u16())) {
+            Ok(entry) => entry,
+            Err(e) => {
+                return MonthStatus::Retry {
+                    reason: format!("body isn't a month of articles: {e}"),
+                    after: None,
+                }
+            }
+        };
+        let stored = match compression.compress(&body) {
+            Ok(stored) => stored,
+            Err(e) => return MonthStatus::Fatal(format!("couldn't compress: {e}")),
+        };
+        // Written whole or not at all, otherwise a crash leaves a file that looks done
+        let path = compression.path_for(date);
+        if let Err(e) = write_atomic(&path, &stored) {
+            return MonthStatus::Fatal(format!("couldn't write {:?}: {e}", path));
+        }
+        manifest.insert(date, entry);
+        if let Err(e) = manifest.save() {
+            return MonthStatus::Fatal(format!("couldn't save the manifest: {e}"));
+        }
+        MonthStatus::Downloaded
+    }
+
+    /// Keeps at `handle_month` through anything retryable, waiting out `backoff` in between.
+    /// `Ok(true)` if the month was downloaded, `Ok(false)` if we already had it.
+    pub async fn fetch_month(
+        &self,
+        date: NaiveDate,
+        compression: Compression,
+        manifest: &mut Manifest,
+        backoff: Backoff,
+        max_retries: u32,
+    ) -> anyhow::Result<bool> {
+        let (year, month) = (date.year(), date.month());
+        let mut retries = 0;
+        loop {
+            match self.handle_month(date, compression, manifest).await {
+                MonthStatus::AlreadyExists => return Ok(false),
+                MonthStatus::Downloaded => return Ok(true),
+                MonthStatus::Retry { reason, after } => {
+                    if retries >= max_retries {
+                        return Err(anyhow::anyhow!(
+                            "Gave up on {year}/{month} after {retries} retries, last: {reason}"
+                        ));
+                    }
+                    let wait = backoff.wait(retries, after);
+                    println!("{year}/{month}: {reason}, retrying in {}s", wait.as_secs());
+                    tokio::time::sleep(wait).await;
+                    retries += 1;
+                }
+                MonthStatus::Fatal(reason) => {
+                    return Err(anyhow::anyhow!("{year}/{month}: {reason}"));
+                }
+            }
+        }
+    }
+}
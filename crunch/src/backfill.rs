@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{Datelike, NaiveDate};
+use cyclicism::{
+    lenient::DocStats,
+    nyt::{nyt_api_key, nyt_base_url, ScrapedDocs},
+    repo::{get_repository, Repository},
+    search::{
+        search_hits, search_page, SearchQuery, SEARCH_MAX_PAGES, SEARCH_PAGE_SIZE,
+        SEARCH_SLEEP_SECS,
+    },
+};
+use reqwest::Client;
+
+const USAGE: &str = r#"Fill gaps in the archive with the Article Search API.
+
+    cargo run --bin backfill -- range <YYYY-MM-DD> <YYYY-MM-DD> [filter query]
+        upsert everything published in those days (inclusive), optionally narrowed by a
+        filter query like 'section_name:("Sports")'
+    cargo run --bin backfill -- reconcile <YYYY-MM> [--fill]
+        compare the month's archive scrape against search counts, day by day, and with
+        --fill backfill the days the archive is short on
+
+Needs NYT_API_KEY (and NYT_BASE_URL to use mock_nyt). The search API allows 5 requests a
+minute, so expect about a minute per 50 articles."#;
+
+struct Backfill {
+    client: Client,
+    base_url: String,
+    api_key: String,
+}
+impl Backfill {
+    async fn sleep(&self) {
+        tokio::time::sleep(Duration::from_secs(SEARCH_SLEEP_SECS)).await;
+    }
+
+    /// Upserts every result for `query`, returning how many weren't in the db before
+    async fn fill(&self, query: &SearchQuery, repo: &dyn Repository) -> anyhow::Result<usize> {
+        let mut num_new = 0;
+        let mut stats = DocStats::default();
+        let mut page = 0;
+        loop {
+            let results =
+                search_page(&self.client, &self.base_url, &self.api_key, query, page).await?;
+            if page == 0 && results.hits > SEARCH_PAGE_SIZE * SEARCH_MAX_PAGES {
+                println!(
+                    "{} hits for {:?}, but only the first {} are reachable. Narrow it with a filter query.",
+                    results.hits,
+                    query,
+                    SEARCH_PAGE_SIZE * SEARCH_MAX_PAGES
+                );
+            }
+            stats.add(results.stats);
+            for article in results.articles.iter() {
+                if !repo.has_archive_article(&article.uri).await? {
+                    num_new += 1;
+                }
+                repo.upsert_scraped(article).await?;
+            }
+            page += 1;
+            let num_pages = results
+                .hits
+                .div_ceil(SEARCH_PAGE_SIZE)
+                .min(SEARCH_MAX_PAGES);
+            if page >= num_pages || (results.articles.is_empty() && results.stats.skipped == 0) {
+                break;
+            }
+            self.sleep().await;
+        }
+        if stats.coerced > 0 || stats.skipped > 0 {
+            stats.report();
+        }
+        Ok(num_new)
+    }
+
+    async fn range(
+        &self,
+        begin: NaiveDate,
+        end: NaiveDate,
+        fq: Option<String>,
+        repo: &dyn Repository,
+    ) -> anyhow::Result<()> {
+        // A day at a time, so we stay under the 1000 result cap
+        for date in begin.iter_days().take_while(|date| *date <= end) {
+            let query = SearchQuery::day(date).with_fq(fq.clone());
+            let num_new = self.fill(&query, repo).await?;
+            println!("{date}: {num_new} new articles");
+            self.sleep().await;
+        }
+        Ok(())
+    }
+
+    async fn reconcile(
+        &self,
+        first: NaiveDate,
+        fill: bool,
+        repo: &dyn Repository,
+    ) -> anyhow::Result<()> {
+        let archive_counts = archive_counts_by_day(first).await?;
+
+        println!("{:<12} {:>8} {:>8}", "day", "archive", "search");
+        let mut short_days = vec![];
+        for date in first
+            .iter_days()
+            .take_while(|date| date.month() == first.month())
+        {
+            let hits = search_hits(
+                &self.client,
+                &self.base_url,
+                &self.api_key,
+                &SearchQuery::day(date),
+            )
+            .await?;
+            let archived = archive_counts.get(&date).copied().unwrap_or(0);
+            let flag = if (hits as usize) > archived {
+                "  <-"
+            } else {
+                ""
+            };
+            println!("{:<12} {:>8} {:>8}{flag}", date.to_string(), archived, hits);
+            if (hits as usize) > archived {
+                short_days.push(date);
+            }
+            self.sleep().await;
+        }
+        println!("The archive is short on {} days", short_days.len());
+
+        if fill {
+            for date in short_days {
+                let num_new = self.fill(&SearchQuery::day(date), repo).await?;
+                println!("{date}: {num_new} new articles");
+                self.sleep().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many docs the month's archive scrape has for each day
+async fn archive_counts_by_day(first: NaiveDate) -> anyhow::Result<BTreeMap<NaiveDate, usize>> {
+    let mut counts = BTreeMap::new();
+    let mut docs = ScrapedDocs::open(first)?;
+    while let Some(article) = docs.next().await {
+        let article = article?;
+        // Just the date part, the offsets aren't consistent across the years
+        let Some(day) = article
+            .pub_date
+            .get(..10)
+            .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        *counts.entry(day).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+fn parse_day(s: &str) -> anyhow::Result<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(s, "%Y-%m-%d")?)
+}
+
+enum Command {
+    Range {
+        begin: NaiveDate,
+        end: NaiveDate,
+        fq: Option<String>,
+    },
+    Reconcile {
+        first: NaiveDate,
+        fill: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    let command = match args.as_slice() {
+        ["range", begin, end, rest @ ..] => Command::Range {
+            begin: parse_day(begin)?,
+            end: parse_day(end)?,
+            fq: rest.first().map(|fq| fq.to_string()),
+        },
+        ["reconcile", month, rest @ ..] => Command::Reconcile {
+            first: parse_day(&format!("{month}-01"))?,
+            fill: rest.first() == Some(&"--fill"),
+        },
+        _ => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+
+    let backfill = Backfill {
+        client: Client::new(),
+        base_url: nyt_base_url(),
+        api_key: nyt_api_key()?,
+    };
+    let repo = get_repository(2).await?;
+    repo.apply_migrations().await?;
+    match command {
+        Command::Range { begin, end, fq } => backfill.range(begin, end, fq, repo.as_ref()).await?,
+        Command::Reconcile { first, fill } => {
+            backfill.reconcile(first, fill, repo.as_ref()).await?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use std::{path::Path, sync::Arc};
+
+use cyclicism::{
+    embed::{embed_async, Embedder, FastEmbedder},
+    golden::{load_golden_set, score_golden_set, BenchMetrics, GoldenQuery},
+    multidrant::MultiCollection,
+    mydrant::{BedSource, Candidate, Collection, CommonInfo},
+    repo::{get_repository, Repository},
+    rerank::Reranker,
+};
+use fastembed::{EmbeddingModel, RerankerModel};
+use qdrant_client::{qdrant::Distance, Qdrant};
+
+const DISTANCE: Distance = Distance::Cosine;
+const K: usize = 10;
+/// How many results get handed to the reranker, for retrievers that have one
+const RERANK_TOP_N: usize = 30;
+const RESULTS_DIR: &str = "bench_results";
+
+/// Which collection a retriever searches
+enum Layout {
+    /// The usual one-source collection
+    Single(BedSource),
+    /// The multi-vector collection, scored by a weighted sum over named vectors
+    Multi(&'static [(BedSource, f32)]),
+}
+
+/// A way of going from a contemporary headline to ranked archive articles
+struct RetrieverSpec {
+    name: &'static str,
+    model: EmbeddingModel,
+    layout: Layout,
+    rerank: Option<RerankerModel>,
+}
+
+/// Add new configurations here. Each one's collection needs to have been built by the embeddor.
+const RETRIEVERS: &[RetrieverSpec] = &[
+    RetrieverSpec {
+        name: "headline-gte",
+        model: EmbeddingModel::GTELargeENV15Q,
+        layout: Layout::Single(BedSource::HeadlineMain),
+        rerank: None,
+    },
+    RetrieverSpec {
+        name: "headline-gte-rerank",
+        model: EmbeddingModel::GTELargeENV15Q,
+        layout: Layout::Single(BedSource::HeadlineMain),
+        rerank: Some(RerankerModel::BGERerankerBase),
+    },
+    RetrieverSpec {
+        name: "snippet-gte",
+        model: EmbeddingModel::GTELargeENV15Q,
+        layout: Layout::Multi(&[(BedSource::Snippet, 1.0)]),
+        rerank: None,
+    },
+    RetrieverSpec {
+        name: "multi-gte",
+        model: EmbeddingModel::GTELargeENV15Q,
+        layout: Layout::Multi(&[
+            (BedSource::HeadlineMain, 0.6),
+            (BedSource::Snippet, 0.3),
+            (BedSource::Keywords, 0.1),
+        ]),
+        rerank: None,
+    },
+];
+
+const USAGE: &str = r#"Score retrievers against a golden set of contemporary headlines -> relevant archive uris.
+
+    cargo run --bin bench -- <golden.jsonl> [retriever names...]
+
+With no names, every retriever in RETRIEVERS is run. Results are printed and saved to bench_results/."#;
+
+#[derive(Debug, serde::Serialize)]
+struct BenchRow {
+    retriever: String,
+    metrics: BenchMetrics,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct BenchRun {
+    golden_set: String,
+    timestamp: String,
+    rows: Vec<BenchRow>,
+}
+
+/// Just enough of a `Candidate` for the reranker, which doesn't need the beds
+fn to_candidates(results: Vec<(CommonInfo, f32)>) -> Vec<Candidate> {
+    results
+        .into_iter()
+        .map(|(info, score)| Candidate {
+            info,
+            score,
+            rerank_score: None,
+            bed: vec![],
+        })
+        .collect()
+}
+
+/// Runs every golden query through the retriever, returning ranked uris per query
+async fn retrieve(
+    spec: &RetrieverSpec,
+    golden: &[GoldenQuery],
+    repo: &dyn Repository,
+) -> anyhow::Result<Vec<Vec<String>>> {
+    let embedder: Arc<dyn Embedder> = Arc::new(FastEmbedder::try_new(spec.model.clone())?);
+    let reranker = match spec.rerank.as_ref() {
+        Some(model) => Some(Reranker::try_new(model.clone())?),
+        None => None,
+    };
+    let fetch = if reranker.is_some() { RERANK_TOP_N } else { K } as u64;
+    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+    let beds = embed_async(
+        embedder.clone(),
+        golden.iter().map(|q| q.query.clone()).collect(),
+    )
+    .await?;
+
+    let mut ranked = vec![];
+    match spec.layout {
+        Layout::Single(source) => {
+            let collection = Collection::new(source, embedder.as_ref(), DISTANCE, qdrant);
+            collection.verify().await?;
+            for bed in beds {
+                ranked.push(to_candidates(collection.top_k(bed, fetch).await?));
+            }
+        }
+        Layout::Multi(weights) => {
+            let sources = weights.iter().map(|(source, _)| *source).collect();
+            let collection = MultiCollection::new(sources, embedder.as_ref(), DISTANCE, qdrant);
+            for bed in beds {
+                ranked.push(to_candidates(
+                    collection.top_k_weighted(bed, weights, fetch).await?,
+                ));
+            }
+        }
+    }
+
+    let mut ranked_uris = vec![];
+    for (query, candidates) in golden.iter().zip(ranked) {
+        let candidates = match reranker.as_ref() {
+            Some(reranker) => {
+                reranker
+                    .rerank(&query.query, candidates, RERANK_TOP_N, repo)
+                    .await?
+            }
+            None => candidates,
+        };
+        ranked_uris.push(candidates.into_iter().map(|c| c.info.uri).collect());
+    }
+    Ok(ranked_uris)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some((golden_path, names)) = args.split_first() else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+    let golden = load_golden_set(Path::new(golden_path))?;
+    let repo = get_repository(2).await?;
+
+    let mut rows = vec![];
+    for spec in RETRIEVERS
+        .iter()
+        .filter(|spec| names.is_empty() || names.iter().any(|n| n == spec.name))
+    {
+        println!("Running {}...", spec.name);
+        let ranked = retrieve(spec, &golden, repo.as_ref()).await?;
+        rows.push(BenchRow {
+            retriever: spec.name.to_string(),
+            metrics: score_golden_set(&golden, &ranked, K),
+        });
+    }
+
+    println!(
+        "\n{:<24} {:>8} {:>8} {:>10}",
+        "retriever",
+        format!("nDCG@{K}"),
+        "MRR",
+        format!("recall@{K}")
+    );
+    for row in rows.iter() {
+        println!(
+            "{:<24} {:>8.4} {:>8.4} {:>10.4}",
+            row.retriever, row.metrics.ndcg, row.metrics.mrr, row.metrics.recall
+        );
+    }
+
+    let run = BenchRun {
+        golden_set: golden_path.clone(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        rows,
+    };
+    std::fs::create_dir_all(RESULTS_DIR)?;
+    let out_path = Path::new(RESULTS_DIR).join(format!(
+        "{}.json",
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    std::fs::write(&out_path, serde_json::to_string_pretty(&run)?)?;
+    println!("\nSaved to {:?}", out_path);
+    Ok(())
+}
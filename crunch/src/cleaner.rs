@@ -3,10 +3,10 @@ use std::sync::Arc;
 use chrono::NaiveDate;
 use cyclicism::{
     get_date, get_json_path,
-    nyt::ScrapedJson,
-    pg::{apply_migrations, get_pg_pool},
+    lenient::DocStats,
+    nyt::ScrapedDocs,
+    repo::{get_repository, Repository},
 };
-use sqlx::PgPool;
 use tokio::{
     sync::{
         mpsc::{channel, Receiver, Sender},
@@ -24,9 +24,10 @@ async fn error_thread(mut rx: Receiver<(String, String)>) {
 }
 
 async fn worker_thread(
-    conn: Arc<PgPool>,
+    repo: Arc<dyn Repository>,
     data: Arc<Mutex<Vec<NaiveDate>>>,
     tx: Sender<(String, String)>,
+    doc_stats: Arc<std::sync::Mutex<DocStats>>,
 ) {
     loop {
         let date = {
@@ -37,7 +38,7 @@ async fn worker_thread(
             println!("{} left!", lock.len());
             date
         };
-        let scraped = match ScrapedJson::from_date(date) {
+        let mut docs = match ScrapedDocs::open(date) {
             Ok(val) => val,
             Err(e) => {
                 tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
@@ -46,21 +47,26 @@ async fn worker_thread(
                 break;
             }
         };
-        for article in &scraped.response.docs {
-            if let Err(e) = article.upsert(&conn).await {
+        while let Some(article) = docs.next().await {
+            let res = match article {
+                Ok(article) => repo.upsert_scraped(&article).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
                 tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
                     .await
                     .ok();
                 break;
             }
         }
+        doc_stats.lock().unwrap().record(date, docs.stats());
     }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let pool: Arc<PgPool> = Arc::new(get_pg_pool(NUM_WORKERS + 2).await?.into());
-    apply_migrations(&pool).await?;
+    let repo = get_repository(NUM_WORKERS + 2).await?;
+    repo.apply_migrations().await?;
 
     let mut raw_data = vec![];
     for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
@@ -72,13 +78,20 @@ async fn main() -> anyhow::Result<()> {
         }
     }
     let data = Arc::new(Mutex::new(raw_data));
+    let doc_stats = Arc::new(std::sync::Mutex::new(DocStats::default()));
     let mut set = JoinSet::new();
     let (tx, rx) = channel(64);
     set.spawn(error_thread(rx));
     for _ in 0..NUM_WORKERS {
-        set.spawn(worker_thread(pool.clone(), data.clone(), tx.clone()));
+        set.spawn(worker_thread(
+            repo.clone(),
+            data.clone(),
+            tx.clone(),
+            doc_stats.clone(),
+        ));
     }
     drop(tx); // If we don't drop this the error thread never dies...
-    while let Some(_) = set.join_next().await {}
+    while set.join_next().await.is_some() {}
+    doc_stats.lock().unwrap().report();
     Ok(())
 }
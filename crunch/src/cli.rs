@@ -1,24 +1,52 @@
 use cyclicism::{
-    mydrant::{BedSource, Collection},
-    nyt::FrontendArticle,
-    pg::get_pg_pool,
+    dedup::collapse_candidates,
+    embed::{embed_one, Embedder, FastEmbedder},
+    flat::FlatStore,
+    mydrant::{BedSource, Collection, IndexConfig},
+    repo::get_repository,
+    rerank::Reranker,
+    store::VectorStore,
 };
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use fastembed::{EmbeddingModel, RerankerModel};
 use qdrant_client::{qdrant::Distance, Qdrant};
-use sqlx::PgPool;
+use std::{path::Path, sync::Arc};
 
 const BED_SOURCE: BedSource = BedSource::HeadlineMain;
-const BED_DIM: u64 = 1024;
 const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
 const DISTANCE: Distance = Distance::Cosine;
+/// How qdrant should store and search the collection, see `IndexConfig`
+const INDEX_CONFIG: IndexConfig = IndexConfig::DEFAULT;
+/// Set to `Some(path)` to search a `FlatStore` file (see the embeddor) instead of qdrant
+const LOCAL_STORE: Option<&str> = None;
+const NUM_RESULTS: usize = 5;
+/// Set to `None` to just show the raw qdrant ordering
+const RERANK_MODEL: Option<RerankerModel> = Some(RerankerModel::BGERerankerBase);
+/// How many qdrant results get handed to the reranker
+const RERANK_TOP_N: u64 = 30;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
-    let collection = Collection::new(BED_SOURCE, BED_DIM, BED_MODEL, DISTANCE, qdrant);
-    let pool: PgPool = get_pg_pool(6).await?.into();
-    let loaded_model =
-        TextEmbedding::try_new(InitOptions::new(BED_MODEL).with_show_download_progress(true))?;
+    let embedder: Arc<dyn Embedder> = Arc::new(FastEmbedder::try_new(BED_MODEL)?);
+    let collection: Box<dyn VectorStore> = match LOCAL_STORE {
+        Some(path) => Box::new(FlatStore::open(
+            Path::new(path),
+            BED_SOURCE,
+            embedder.as_ref(),
+            DISTANCE,
+        )?),
+        None => {
+            let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+            let collection = Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant)
+                .with_index_config(INDEX_CONFIG);
+            collection.verify().await?;
+            Box::new(collection)
+        }
+    };
+    let repo = get_repository(6).await?;
+    let reranker = match RERANK_MODEL {
+        Some(model) => Some(Reranker::try_new(model)?),
+        None => None,
+    };
     loop {
         let mut raw_input = String::new();
         println!("Enter a headline: ");
@@ -27,19 +55,32 @@ async fn main() -> anyhow::Result<()> {
         if input == "quit" {
             break;
         }
-        let bed = loaded_model
-            .embed(vec![input], None)?
-            .into_iter()
-            .next()
-            .unwrap();
-        let infos = collection.top_k(bed, 5).await?;
+        let bed = embed_one(embedder.clone(), input.to_string()).await?;
+        let mut candidates = match reranker.as_ref() {
+            Some(reranker) => {
+                let candidates = collection.top_k_candidates(bed, RERANK_TOP_N).await?;
+                let candidates = collapse_candidates(candidates, repo.as_ref()).await?;
+                reranker
+                    .rerank(input, candidates, RERANK_TOP_N as usize, repo.as_ref())
+                    .await?
+            }
+            None => {
+                let candidates = collection.top_k_candidates(bed, RERANK_TOP_N).await?;
+                collapse_candidates(candidates, repo.as_ref()).await?
+            }
+        };
+        candidates.truncate(NUM_RESULTS);
         let mut articles = vec![];
-        for info in infos {
-            let article = FrontendArticle::from_uri(&info.uri, &pool).await?;
-            articles.push(article);
+        for candidate in candidates {
+            let article = repo.archive_article(&candidate.info.uri).await?;
+            articles.push((article, candidate.score, candidate.rerank_score));
         }
-        for (ix, article) in articles.into_iter().enumerate() {
+        for (ix, (article, score, rerank_score)) in articles.into_iter().enumerate() {
             println!("Result {ix}");
+            match rerank_score {
+                Some(rerank_score) => println!("Score: {score:.4} (rerank {rerank_score:.4})"),
+                None => println!("Score: {score:.4}"),
+            }
             println!("Headline: {}", article.headline_main);
             println!("Date: {}/{}/{}", article.month, article.day, article.year);
             println!("Snippet: {}", article.snippet);
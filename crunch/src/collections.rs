@@ -0,0 +1,231 @@
+use std::{path::Path, sync::Arc};
+
+use cyclicism::{
+    embed::{Embedder, FastEmbedder},
+    multidrant::MultiCollection,
+    mydrant::{BedSource, Collection},
+    portable::{export_collection, import_collection},
+};
+use fastembed::EmbeddingModel;
+use qdrant_client::{qdrant::Distance, Qdrant};
+
+const BED_SOURCE: BedSource = BedSource::HeadlineMain;
+const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
+const DISTANCE: Distance = Distance::Cosine;
+/// For `multi ...`, should match the embeddor's `MULTI_SOURCES`
+const MULTI_SOURCES: &[BedSource] = &[BedSource::HeadlineMain, BedSource::Snippet];
+/// A new build with fewer points than this fraction of the live one probably died partway through
+const MIN_FRACTION_OF_LIVE: f64 = 0.95;
+/// Snapshot files go over qdrant's REST API rather than gRPC
+const QDRANT_REST_URL: &str = "http://localhost:6333";
+
+const USAGE: &str = r#"Manage blue/green builds of the archive collection.
+
+To build a new version, run the embeddor with a version (or "new"):
+    cargo run --bin embeddor -- new
+
+Then:
+    cargo run --bin collections -- list
+    cargo run --bin collections -- validate <version>
+    cargo run --bin collections -- promote <version>
+    cargo run --bin collections -- rollback
+
+Put "multi" first to do the same to the multi-vector collection, e.g:
+    cargo run --bin collections -- multi promote <version>
+
+Backups (no version means whatever is live):
+    cargo run --bin collections -- snapshot [version]
+        Has qdrant snapshot the collection and downloads it to <collection>.snapshot
+    cargo run --bin collections -- restore <file.snapshot> <version>
+        Uploads a snapshot into a version, e.g on a fresh qdrant. Promote it afterwards.
+    cargo run --bin collections -- export <file> [version]
+        Writes vectors + payloads to a plain file that doesn't depend on qdrant's format
+    cargo run --bin collections -- import <file> <version>"#;
+
+fn make_collection(embedder: &dyn Embedder) -> anyhow::Result<Collection> {
+    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+    Ok(Collection::new(BED_SOURCE, embedder, DISTANCE, qdrant))
+}
+
+/// Either kind of collection, for the commands that work the same on both
+enum Managed {
+    Single(Collection),
+    Multi(MultiCollection),
+}
+impl Managed {
+    fn new(multi: bool, embedder: &dyn Embedder) -> anyhow::Result<Self> {
+        Ok(match multi {
+            true => {
+                let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+                Managed::Multi(MultiCollection::new(
+                    MULTI_SOURCES.to_vec(),
+                    embedder,
+                    DISTANCE,
+                    qdrant,
+                ))
+            }
+            false => Managed::Single(make_collection(embedder)?),
+        })
+    }
+
+    fn with_version(self, version: &str) -> Self {
+        match self {
+            Managed::Single(collection) => Managed::Single(collection.with_version(version)),
+            Managed::Multi(collection) => Managed::Multi(collection.with_version(version)),
+        }
+    }
+
+    fn alias_name(&self) -> String {
+        match self {
+            Managed::Single(collection) => collection.alias_name(),
+            Managed::Multi(collection) => collection.alias_name(),
+        }
+    }
+
+    async fn list_versions(&self) -> anyhow::Result<Vec<String>> {
+        match self {
+            Managed::Single(collection) => collection.list_versions().await,
+            Managed::Multi(collection) => collection.list_versions().await,
+        }
+    }
+
+    async fn live_version(&self) -> anyhow::Result<Option<String>> {
+        match self {
+            Managed::Single(collection) => collection.live_version().await,
+            Managed::Multi(collection) => collection.live_version().await,
+        }
+    }
+
+    async fn point_count(&self) -> anyhow::Result<u64> {
+        match self {
+            Managed::Single(collection) => collection.point_count().await,
+            Managed::Multi(collection) => collection.point_count().await,
+        }
+    }
+
+    async fn verify(&self) -> anyhow::Result<()> {
+        match self {
+            Managed::Single(collection) => collection.verify().await,
+            Managed::Multi(collection) => collection.verify().await,
+        }
+    }
+
+    async fn promote(&self) -> anyhow::Result<()> {
+        match self {
+            Managed::Single(collection) => collection.promote().await,
+            Managed::Multi(collection) => collection.promote().await,
+        }
+    }
+
+    async fn rollback(&self) -> anyhow::Result<String> {
+        match self {
+            Managed::Single(collection) => collection.rollback().await,
+            Managed::Multi(collection) => collection.rollback().await,
+        }
+    }
+}
+
+async fn list(multi: bool, embedder: &dyn Embedder) -> anyhow::Result<()> {
+    let collection = Managed::new(multi, embedder)?;
+    let live = collection.live_version().await?;
+    println!("{}", collection.alias_name());
+    for version in collection.list_versions().await? {
+        let count = Managed::new(multi, embedder)?
+            .with_version(&version)
+            .point_count()
+            .await?;
+        let marker = if live.as_ref() == Some(&version) {
+            " (live)"
+        } else {
+            ""
+        };
+        println!("  {version}: {count} points{marker}");
+    }
+    Ok(())
+}
+
+/// Checks that a build is safe to promote: right shape, not empty, and not much smaller than what's live
+async fn validate(multi: bool, embedder: &dyn Embedder, version: &str) -> anyhow::Result<()> {
+    let candidate = Managed::new(multi, embedder)?.with_version(version);
+    candidate.verify().await?;
+    let count = candidate.point_count().await?;
+    if count == 0 {
+        return Err(anyhow::anyhow!("{version} is empty"));
+    }
+    let live = Managed::new(multi, embedder)?;
+    if let Some(live_version) = live.live_version().await? {
+        let live_count = live.point_count().await?;
+        if (count as f64) < (live_count as f64) * MIN_FRACTION_OF_LIVE {
+            return Err(anyhow::anyhow!(
+                "{version} has {count} points, but live ({live_version}) has {live_count}"
+            ));
+        }
+    }
+    println!("{version} looks good ({count} points)");
+    Ok(())
+}
+
+fn with_version(collection: Collection, version: Option<&&str>) -> Collection {
+    match version {
+        Some(version) => collection.with_version(version),
+        None => collection,
+    }
+}
+
+async fn snapshot(embedder: &dyn Embedder, version: Option<&&str>) -> anyhow::Result<()> {
+    let collection = with_version(make_collection(embedder)?, version);
+    let snapshot = collection.create_snapshot().await?;
+    let out_path = format!("{}.snapshot", collection.alias_name());
+    collection
+        .download_snapshot(&snapshot, Path::new(&out_path), QDRANT_REST_URL)
+        .await?;
+    println!("Saved {snapshot} to {out_path}");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    let (multi, args) = match args.as_slice() {
+        ["multi", rest @ ..] => (true, rest),
+        args => (false, args),
+    };
+    let embedder: Arc<dyn Embedder> = Arc::new(FastEmbedder::try_new(BED_MODEL)?);
+    match (multi, args) {
+        (_, ["list"]) => list(multi, embedder.as_ref()).await?,
+        (_, ["validate", version]) => validate(multi, embedder.as_ref(), version).await?,
+        (_, ["promote", version]) => {
+            validate(multi, embedder.as_ref(), version).await?;
+            Managed::new(multi, embedder.as_ref())?
+                .with_version(version)
+                .promote()
+                .await?;
+            println!("{version} is now live");
+        }
+        (_, ["rollback"]) => {
+            let version = Managed::new(multi, embedder.as_ref())?.rollback().await?;
+            println!("Rolled back, {version} is now live");
+        }
+        (false, ["snapshot", rest @ ..]) => snapshot(embedder.as_ref(), rest.first()).await?,
+        (false, ["restore", path, version]) => {
+            make_collection(embedder.as_ref())?
+                .with_version(version)
+                .restore_snapshot(Path::new(path), QDRANT_REST_URL)
+                .await?;
+            println!("Restored {path} as {version}");
+        }
+        (false, ["export", path, rest @ ..]) => {
+            let collection = with_version(make_collection(embedder.as_ref())?, rest.first());
+            let count = export_collection(&collection, Path::new(path)).await?;
+            println!("Exported {count} points to {path}");
+        }
+        (false, ["import", path, version]) => {
+            let collection = make_collection(embedder.as_ref())?.with_version(version);
+            let count = import_collection(&collection, Path::new(path)).await?;
+            println!("Imported {count} points into {version}");
+        }
+        _ => println!("{USAGE}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::PathBuf,
+};
+
+use chrono::NaiveDate;
+
+use crate::get_json_path;
+
+/// zstd level for new scrapes. 19 is slow to write but these only get written once.
+const ZSTD_LEVEL: i32 = 19;
+
+/// How a month's scrape is stored on disk. The json is the same either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Plain,
+    Gzip,
+    Zstd,
+}
+impl Compression {
+    pub const ALL: [Compression; 3] = [Compression::Plain, Compression::Gzip, Compression::Zstd];
+
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "plain" | "none" => Ok(Compression::Plain),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(anyhow::anyhow!(
+                "Unknown compression {name}, expected plain, gzip or zstd"
+            )),
+        }
+    }
+
+    /// `scrapes/1980_1.json`, `scrapes/1980_1.json.gz` or `scrapes/1980_1.json.zst`
+    pub fn path_for(&self, date: NaiveDate) -> PathBuf {
+        let plain = get_json_path(date);
+        let ext = match self {
+            Compression::Plain => return plain,
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        };
+        let mut name = plain.into_os_string();
+        name.push(ext);
+        PathBuf::from(name)
+    }
+
+    pub fn compress(&self, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Compression::Plain => bytes.to_vec(),
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Compression::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL)?,
+        })
+    }
+
+    pub fn decompress(&self, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Compression::Plain => bytes.to_vec(),
+            Compression::Gzip => {
+                let mut out = vec![];
+                flate2::read::GzDecoder::new(bytes).read_to_end(&mut out)?;
+                out
+            }
+            Compression::Zstd => zstd::decode_all(bytes)?,
+        })
+    }
+}
+
+/// Wherever this month's scrape is, in whatever format
+pub fn find_scrape(date: NaiveDate) -> Option<(PathBuf, Compression)> {
+    Compression::ALL
+        .iter()
+        .map(|compression| (compression.path_for(date), *compression))
+        .find(|(path, _)| path.exists())
+}
+
+fn find_scrape_or_err(date: NaiveDate) -> anyhow::Result<(PathBuf, Compression)> {
+    find_scrape(date).ok_or(anyhow::anyhow!(
+        "No scrape for {:?} (plain or compressed)",
+        get_json_path(date)
+    ))
+}
+
+/// The json for this month, decompressed if need be
+pub fn read_scrape(date: NaiveDate) -> anyhow::Result<Vec<u8>> {
+    let (path, compression) = find_scrape_or_err(date)?;
+    compression.decompress(&std::fs::read(path)?)
+}
+
+/// A buffered reader over this month's json, decompressing as it goes
+pub fn open_scrape(date: NaiveDate) -> anyhow::Result<Box<dyn Read + Send>> {
+    let (path, compression) = find_scrape_or_err(date)?;
+    let file = BufReader::new(File::open(path)?);
+    Ok(match compression {
+        Compression::Plain => Box::new(file),
+        Compression::Gzip => Box::new(BufReader::new(flate2::read::GzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(
+            file,
+        )?)),
+    })
+}
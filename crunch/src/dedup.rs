@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+
+use crate::{
+    diversify::cosine_similarity,
+    mydrant::{Candidate, CommonInfo, DetailedEmbedding},
+    repo::Repository,
+};
+
+/// When two archive articles count as the same story
+#[derive(Debug, Clone, Copy)]
+pub struct DedupConfig {
+    /// Only articles published at most this many days apart can be duplicates
+    pub window_days: i64,
+    /// Articles this similar (cosine) are duplicates even if the headlines differ a bit
+    pub min_similarity: f32,
+}
+impl DedupConfig {
+    pub const DEFAULT: Self = Self {
+        window_days: 2,
+        min_similarity: 0.97,
+    };
+}
+
+/// Lowercases and strips punctuation, so "Reagan Signs Tax Bill." and "REAGAN SIGNS TAX BILL" match
+pub fn normalize_headline(headline: &str) -> String {
+    headline
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn info_date(info: &CommonInfo) -> NaiveDate {
+    NaiveDate::from_ymd_opt(info.year as i32, info.month, info.day).unwrap_or_default()
+}
+
+fn find_root(parents: &mut [usize], ix: usize) -> usize {
+    let mut root = ix;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // Path compression, so long chains of reprints stay cheap
+    let mut ix = ix;
+    while parents[ix] != root {
+        let next = parents[ix];
+        parents[ix] = root;
+        ix = next;
+    }
+    root
+}
+
+/// Groups articles that share a normalized headline or are nearly the same vector, as long as they
+/// were published within `config.window_days` of each other. Returns `(uri, canonical_uri)` for
+/// every article that isn't its own canonical, where the canonical is the earliest in its group.
+///
+/// `headlines` maps uri -> headline, articles missing from it only get compared by vector.
+pub fn find_duplicates(
+    data: &[DetailedEmbedding],
+    headlines: &HashMap<String, String>,
+    config: DedupConfig,
+) -> Vec<(String, String)> {
+    // Earliest first (ties broken by uri), so whatever is first in a group is its canonical
+    let mut order = (0..data.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        let (a, b) = (&data[*a].info, &data[*b].info);
+        info_date(a).cmp(&info_date(b)).then(a.uri.cmp(&b.uri))
+    });
+    let dates = order
+        .iter()
+        .map(|ix| info_date(&data[*ix].info))
+        .collect::<Vec<_>>();
+    let normalized = order
+        .iter()
+        .map(|ix| {
+            headlines
+                .get(&data[*ix].info.uri)
+                .map(|h| normalize_headline(h))
+        })
+        .collect::<Vec<_>>();
+
+    let mut parents = (0..order.len()).collect::<Vec<_>>();
+    let mut window_start = 0;
+    for i in 0..order.len() {
+        while (dates[i] - dates[window_start]).num_days() > config.window_days {
+            window_start += 1;
+        }
+        for j in window_start..i {
+            let same_headline = match (&normalized[i], &normalized[j]) {
+                (Some(a), Some(b)) => !a.is_empty() && a == b,
+                _ => false,
+            };
+            if same_headline
+                || cosine_similarity(&data[order[i]].bed, &data[order[j]].bed)
+                    >= config.min_similarity
+            {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                if root_i != root_j {
+                    // Lower index is earlier, so it stays the root
+                    parents[root_i.max(root_j)] = root_i.min(root_j);
+                }
+            }
+        }
+    }
+
+    (0..order.len())
+        .filter_map(|i| {
+            let root = find_root(&mut parents, i);
+            (root != i).then(|| {
+                (
+                    data[order[i]].info.uri.clone(),
+                    data[order[root]].info.uri.clone(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Keeps only the best-ranked member of each duplicate group, pointed at the canonical uri.
+/// `items` must already be sorted best first.
+async fn collapse<T>(
+    items: Vec<T>,
+    info_of: impl Fn(&mut T) -> &mut CommonInfo,
+    repo: &dyn Repository,
+) -> anyhow::Result<Vec<T>> {
+    let mut items = items;
+    let uris = items
+        .iter_mut()
+        .map(|item| info_of(item).uri.clone())
+        .collect::<Vec<_>>();
+    let canonical = repo.canonical_uris(&uris).await?;
+    let mut seen = HashSet::new();
+    let mut kept = vec![];
+    for mut item in items {
+        let info = info_of(&mut item);
+        if let Some(canonical_uri) = canonical.get(&info.uri) {
+            info.uri = canonical_uri.clone();
+        }
+        if seen.insert(info.uri.clone()) {
+            kept.push(item);
+        }
+    }
+    Ok(kept)
+}
+
+/// Collapses `Collection::top_k` style results down to one per story
+pub async fn collapse_results(
+    results: Vec<(CommonInfo, f32)>,
+    repo: &dyn Repository,
+) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+    collapse(results, |(info, _)| info, repo).await
+}
+
+/// Collapses candidates down to one per story, keeping the order they came in
+pub async fn collapse_candidates(
+    candidates: Vec<Candidate>,
+    repo: &dyn Repository,
+) -> anyhow::Result<Vec<Candidate>> {
+    collapse(candidates, |candidate| &mut candidate.info, repo).await
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use cyclicism::{
+    dedup::{find_duplicates, DedupConfig},
+    embed::{Embedder, FastEmbedder},
+    mydrant::{BedSource, Collection},
+    repo::get_repository,
+};
+use fastembed::EmbeddingModel;
+use qdrant_client::{qdrant::Distance, Qdrant};
+
+const BED_SOURCE: BedSource = BedSource::HeadlineMain;
+const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
+const DISTANCE: Distance = Distance::Cosine;
+const DEDUP_CONFIG: DedupConfig = DedupConfig::DEFAULT;
+
+/// Finds reprints of the same story across the archive and records their canonical uri,
+/// one year at a time (so reprints straddling New Year's are missed, which is rare enough)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let repo = get_repository(2).await?;
+    repo.apply_migrations().await?;
+    let embedder: Arc<dyn Embedder> = Arc::new(FastEmbedder::try_new(BED_MODEL)?);
+    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+    let collection = Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant);
+    collection.verify().await?;
+
+    let mut total = 0;
+    for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
+        let data = collection.export_years(year, year).await?;
+        let uris = data.iter().map(|d| d.info.uri.clone()).collect::<Vec<_>>();
+        let headlines = repo.archive_headlines(&uris).await?;
+        let mapping = find_duplicates(&data, &headlines, DEDUP_CONFIG);
+        repo.store_canonical_uris(&mapping).await?;
+        println!(
+            "{year}: {} duplicates among {} articles",
+            mapping.len(),
+            data.len()
+        );
+        total += mapping.len();
+    }
+    println!("{total} duplicates in total");
+    Ok(())
+}
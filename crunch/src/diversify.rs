@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::mydrant::Candidate;
+
+/// How hard we should try to spread matches out across the archive
+#[derive(Debug, Clone, Copy)]
+pub enum Diversity {
+    /// Just take the best scores, even if they're all from the same week
+    Off,
+    /// Maximal marginal relevance. A `lambda` of 1.0 is pure relevance, 0.0 is pure novelty.
+    Mmr { lambda: f32 },
+    /// At most `per_bucket` matches from each `span_years` wide window (1 => per-year, 10 => per-decade).
+    /// If there aren't enough buckets to fill `k`, the best leftovers are used.
+    Bucketed { span_years: u32, per_bucket: usize },
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Picks `k` of the candidates according to `diversity`. Candidates are assumed to come sorted
+/// best-first (which is what qdrant and the reranker give us), and the result is also sorted best-first.
+pub fn diversify(candidates: Vec<Candidate>, k: usize, diversity: Diversity) -> Vec<Candidate> {
+    let mut picked = match diversity {
+        Diversity::Off => candidates.into_iter().take(k).collect(),
+        Diversity::Mmr { lambda } => mmr(candidates, k, lambda.clamp(0.0, 1.0)),
+        Diversity::Bucketed {
+            span_years,
+            per_bucket,
+        } => bucketed(candidates, k, span_years.max(1), per_bucket.max(1)),
+    };
+    picked.sort_by(|a, b| b.relevance().total_cmp(&a.relevance()));
+    picked
+}
+
+fn mmr(mut candidates: Vec<Candidate>, k: usize, lambda: f32) -> Vec<Candidate> {
+    let mut picked: Vec<Candidate> = vec![];
+    while picked.len() < k && !candidates.is_empty() {
+        let mut best_ix = 0;
+        let mut best_value = f32::MIN;
+        for (ix, candidate) in candidates.iter().enumerate() {
+            let redundancy = picked
+                .iter()
+                .map(|other| cosine_similarity(&candidate.bed, &other.bed))
+                .fold(0.0, f32::max);
+            let value = lambda * candidate.relevance() - (1.0 - lambda) * redundancy;
+            if value > best_value {
+                best_value = value;
+                best_ix = ix;
+            }
+        }
+        picked.push(candidates.remove(best_ix));
+    }
+    picked
+}
+
+fn bucketed(
+    candidates: Vec<Candidate>,
+    k: usize,
+    span_years: u32,
+    per_bucket: usize,
+) -> Vec<Candidate> {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    let mut picked = vec![];
+    let mut leftovers = vec![];
+    for candidate in candidates {
+        if picked.len() >= k {
+            break;
+        }
+        let count = counts.entry(candidate.info.year / span_years).or_default();
+        if *count < per_bucket {
+            *count += 1;
+            picked.push(candidate);
+        } else {
+            leftovers.push(candidate);
+        }
+    }
+    let missing = k.saturating_sub(picked.len());
+    picked.extend(leftovers.into_iter().take(missing));
+    picked
+}
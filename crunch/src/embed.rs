@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use uuid::Uuid;
+
+/// Anything that can turn text into vectors. Implementations are blocking (fastembed is CPU-bound),
+/// so async code should go through `embed_async` / `embed_one` rather than calling `embed` directly.
+pub trait Embedder: Send + Sync {
+    /// Embeds a batch of texts, returning one vector per text in the same order
+    fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>>;
+    /// The length of every vector this embedder produces
+    fn dim(&self) -> u64;
+    /// A stable name for the model. Collections are named after this, so don't change it lightly.
+    fn model_id(&self) -> String;
+}
+
+/// Runs `embedder.embed` on the blocking pool
+pub async fn embed_async(
+    embedder: Arc<dyn Embedder>,
+    texts: Vec<String>,
+) -> anyhow::Result<Vec<Vec<f32>>> {
+    tokio::task::spawn_blocking(move || embedder.embed(texts)).await?
+}
+
+/// Embeds a single text on the blocking pool
+pub async fn embed_one(embedder: Arc<dyn Embedder>, text: String) -> anyhow::Result<Vec<f32>> {
+    embed_async(embedder, vec![text])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(anyhow::anyhow!("Embedder returned no vectors"))
+}
+
+/// A local model run through fastembed
+pub struct FastEmbedder {
+    model: TextEmbedding,
+    name: EmbeddingModel,
+    dim: u64,
+}
+impl FastEmbedder {
+    pub fn try_new(name: EmbeddingModel) -> anyhow::Result<Self> {
+        let model = TextEmbedding::try_new(
+            InitOptions::new(name.clone()).with_show_download_progress(true),
+        )?;
+        // fastembed knows the dimension of everything it ships, but fall back to a probe just in case
+        let dim = match TextEmbedding::get_model_info(&name) {
+            Ok(info) => info.dim as u64,
+            Err(_) => model
+                .embed(vec!["probe"], None)?
+                .first()
+                .map(|bed| bed.len() as u64)
+                .ok_or(anyhow::anyhow!("{:?} returned nothing for probe", name))?,
+        };
+        Ok(Self { model, name, dim })
+    }
+}
+impl Embedder for FastEmbedder {
+    fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        self.model.embed(texts, None)
+    }
+
+    fn dim(&self) -> u64 {
+        self.dim
+    }
+
+    fn model_id(&self) -> String {
+        // Matches the names of collections made before this trait existed
+        format!("{:?}", self.name)
+    }
+}
+
+/// A deterministic fake that hashes words into buckets. Texts that share words end up close together,
+/// which is enough to exercise the pipeline without downloading a model.
+pub struct HashEmbedder {
+    dim: u64,
+}
+impl HashEmbedder {
+    pub fn new(dim: u64) -> Self {
+        Self { dim: dim.max(1) }
+    }
+
+    /// FNV-1a, because std's hasher isn't guaranteed to be stable across releases
+    fn hash(word: &str) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in word.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut bed = vec![0.0; self.dim as usize];
+        for word in text.split_whitespace() {
+            let hash = Self::hash(&word.to_lowercase());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            bed[(hash % self.dim) as usize] += sign;
+        }
+        let norm = bed.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            bed.iter_mut().for_each(|x| *x /= norm);
+        }
+        bed
+    }
+}
+impl Embedder for HashEmbedder {
+    fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+
+    fn dim(&self) -> u64 {
+        self.dim
+    }
+
+    fn model_id(&self) -> String {
+        format!("Hash{}", self.dim)
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct OpenAiEmbeddingsReq<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OpenAiEmbeddingsResp {
+    data: Vec<OpenAiEmbedding>,
+}
+
+/// Talks to a (local) server that speaks the OpenAI `/v1/embeddings` protocol (llama.cpp, vLLM, ollama...)
+pub struct OpenAiEmbedder {
+    client: reqwest::Client,
+    /// `embed` is blocking, so we hang on to the runtime to drive the (async) requests
+    handle: tokio::runtime::Handle,
+    /// e.g `http://localhost:8080`
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    dim: u64,
+}
+impl OpenAiEmbedder {
+    /// Connects to the server and embeds a probe string to figure out the dimension
+    pub async fn try_new(
+        base_url: &str,
+        model: &str,
+        api_key: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut embedder = Self {
+            client: reqwest::Client::new(),
+            handle: tokio::runtime::Handle::current(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+            dim: 0,
+        };
+        let probe = embedder.request(&["probe".to_string()]).await?;
+        embedder.dim = probe
+            .first()
+            .map(|bed| bed.len() as u64)
+            .ok_or(anyhow::anyhow!(
+                "Embeddings server returned nothing for probe"
+            ))?;
+        Ok(embedder)
+    }
+
+    async fn request(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut req = self
+            .client
+            .post(format!("{}/v1/embeddings", self.base_url))
+            .json(&OpenAiEmbeddingsReq {
+                model: &self.model,
+                input: texts,
+            });
+        if let Some(key) = self.api_key.as_ref() {
+            req = req.bearer_auth(key);
+        }
+        let resp = req.send().await?.error_for_status()?;
+        let mut data = resp.json::<OpenAiEmbeddingsResp>().await?.data;
+        if data.len() != texts.len() {
+            return Err(anyhow::anyhow!(
+                "Asked for {} embeddings, got {}",
+                texts.len(),
+                data.len()
+            ));
+        }
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+impl Embedder for OpenAiEmbedder {
+    fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        self.handle.block_on(self.request(&texts))
+    }
+
+    fn dim(&self) -> u64 {
+        self.dim
+    }
+
+    fn model_id(&self) -> String {
+        format!("OpenAi_{}", self.model.replace(['/', ':'], "_"))
+    }
+}
+
+/// Wraps another embedder with a persistent, content-addressed cache, so identical texts
+/// ("Corrections", "No Headline"...) only ever go through the model once per model.
+///
+/// Each model gets an append-only file of `[16 byte key][dim f32s]` records, where the key hashes
+/// the model id together with the text. Only the key -> offset index lives in memory.
+///
+/// The file is exclusively locked while it's open, since another process appending to it would
+/// leave our index pointing at the wrong records. Whoever opens it second goes without the cache.
+pub struct CachedEmbedder {
+    inner: Box<dyn Embedder>,
+    /// `None` when another process has the cache
+    cache: Option<Mutex<BedCache>>,
+}
+
+struct BedCache {
+    file: File,
+    offsets: HashMap<Uuid, u64>,
+    hits: u64,
+    misses: u64,
+}
+
+impl CachedEmbedder {
+    /// Opens (or starts) the cache for `inner`'s model inside `dir`
+    pub fn try_new(inner: Box<dyn Embedder>, dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.beds", inner.model_id()));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                println!(
+                    "{} is in use by another process, embedding without the cache",
+                    path.display()
+                );
+                return Ok(Self { inner, cache: None });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        let record_len = 16 + inner.dim() * 4;
+        let len = file.metadata()?.len();
+        // A crash mid-write leaves a partial record at the end, which we just drop
+        if len % record_len != 0 {
+            file.set_len(len - len % record_len)?;
+        }
+
+        let mut offsets = HashMap::new();
+        let mut key = [0u8; 16];
+        let mut offset = 0;
+        file.seek(SeekFrom::Start(0))?;
+        while offset < len - len % record_len {
+            file.read_exact(&mut key)?;
+            offsets.insert(Uuid::from_bytes(key), offset);
+            offset += record_len;
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        Ok(Self {
+            inner,
+            cache: Some(Mutex::new(BedCache {
+                file,
+                offsets,
+                hits: 0,
+                misses: 0,
+            })),
+        })
+    }
+
+    fn key(&self, text: &str) -> Uuid {
+        let model_id = self.inner.model_id();
+        Uuid::new_v3(
+            &Uuid::NAMESPACE_OID,
+            [model_id.as_bytes(), &[0], text.as_bytes()]
+                .concat()
+                .as_slice(),
+        )
+    }
+
+    /// (hits, misses) since this embedder was opened
+    pub fn stats(&self) -> (u64, u64) {
+        let Some(cache) = self.cache.as_ref() else {
+            return (0, 0);
+        };
+        let cache = cache.lock().unwrap();
+        (cache.hits, cache.misses)
+    }
+}
+
+impl BedCache {
+    fn get(&mut self, key: &Uuid, dim: u64) -> anyhow::Result<Option<Vec<f32>>> {
+        let Some(offset) = self.offsets.get(key) else {
+            return Ok(None);
+        };
+        let mut bytes = vec![0u8; dim as usize * 4];
+        self.file.seek(SeekFrom::Start(offset + 16))?;
+        self.file.read_exact(&mut bytes)?;
+        Ok(Some(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        ))
+    }
+
+    fn put(&mut self, key: Uuid, bed: &[f32]) -> anyhow::Result<()> {
+        if self.offsets.contains_key(&key) {
+            return Ok(());
+        }
+        let mut record = key.as_bytes().to_vec();
+        record.extend(bed.iter().flat_map(|x| x.to_le_bytes()));
+        // Append mode, so this always lands at the end regardless of where reads left the cursor
+        let offset = self.file.metadata()?.len();
+        self.file.write_all(&record)?;
+        self.offsets.insert(key, offset);
+        Ok(())
+    }
+}
+
+impl Embedder for CachedEmbedder {
+    fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let Some(cache) = self.cache.as_ref() else {
+            return self.inner.embed(texts);
+        };
+        let dim = self.inner.dim();
+        let keys = texts.iter().map(|text| self.key(text)).collect::<Vec<_>>();
+        let mut beds: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+        // Texts that aren't cached, deduplicated so a batch full of "Corrections" is embedded once
+        let mut missing: HashMap<Uuid, String> = HashMap::new();
+        {
+            let mut cache = cache.lock().unwrap();
+            for (ix, key) in keys.iter().enumerate() {
+                match cache.get(key, dim)? {
+                    Some(bed) => {
+                        beds[ix] = Some(bed);
+                        cache.hits += 1;
+                    }
+                    None => {
+                        missing.entry(*key).or_insert_with(|| texts[ix].clone());
+                    }
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let (missing_keys, missing_texts): (Vec<_>, Vec<_>) = missing.into_iter().unzip();
+            // The model is the slow part, so don't hold the lock while it runs
+            let computed = self.inner.embed(missing_texts)?;
+            let mut cache = cache.lock().unwrap();
+            for (key, bed) in missing_keys.iter().zip(computed.iter()) {
+                if bed.len() as u64 != dim {
+                    return Err(anyhow::anyhow!(
+                        "{} returned a {} long vector, expected {}",
+                        self.inner.model_id(),
+                        bed.len(),
+                        dim
+                    ));
+                }
+                cache.put(*key, bed)?;
+            }
+            let computed = missing_keys
+                .into_iter()
+                .zip(computed)
+                .collect::<HashMap<_, _>>();
+            for (ix, key) in keys.iter().enumerate() {
+                if beds[ix].is_none() {
+                    beds[ix] = computed.get(key).cloned();
+                    cache.misses += 1;
+                }
+            }
+        }
+        beds.into_iter()
+            .map(|bed| bed.ok_or(anyhow::anyhow!("Embedder returned too few vectors")))
+            .collect()
+    }
+
+    fn dim(&self) -> u64 {
+        self.inner.dim()
+    }
+
+    fn model_id(&self) -> String {
+        // Same id as the wrapped model, so caching doesn't change which collection gets used
+        self.inner.model_id()
+    }
+}
@@ -1,12 +1,18 @@
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use fastembed::EmbeddingModel;
 use qdrant_client::{qdrant::Distance, Qdrant};
-use std::sync::Arc;
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use chrono::NaiveDate;
 use cyclicism::{
+    embed::{embed_async, CachedEmbedder, Embedder, FastEmbedder},
+    flat::FlatStore,
     get_date, get_json_path,
-    mydrant::{break_article_for_mydrant, BedSource, Collection, DetailedEmbedding},
-    nyt::ScrapedJson,
+    junk::{JunkFilter, JunkRules},
+    lenient::DocStats,
+    multidrant::{break_article_for_multidrant, MultiCollection, MultiEmbedding},
+    mydrant::{break_article_for_mydrant, BedSource, Collection, DetailedEmbedding, IndexConfig},
+    nyt::{ScrapedArticle, ScrapedDocs},
+    store::VectorStore,
 };
 use tokio::{
     sync::{
@@ -22,11 +28,84 @@ async fn error_thread(mut rx: Receiver<(String, String)>) {
     }
 }
 
+/// Where the embeddings end up
+enum Target {
+    /// One vector per point, from a single `BedSource`, in qdrant or a `FlatStore`
+    Single(Box<dyn VectorStore>),
+    /// One named vector per `BedSource` on each point
+    Multi(Box<MultiCollection>),
+}
+impl Target {
+    async fn ensure_created(&self) -> anyhow::Result<()> {
+        match self {
+            Target::Single(collection) => collection.ensure_created().await,
+            Target::Multi(collection) => collection.ensure_created().await,
+        }
+    }
+}
+
+async fn embed_single(
+    collection: &dyn VectorStore,
+    embedder: Arc<dyn Embedder>,
+    junk: &JunkFilter,
+    chunk: Vec<ScrapedArticle>,
+) -> anyhow::Result<()> {
+    let mut documents = vec![];
+    let mut broad_details = vec![];
+    for (uri, text, info) in chunk
+        .into_iter()
+        .filter_map(|article| break_article_for_mydrant(article, BED_SOURCE, junk))
+    {
+        documents.push(text);
+        broad_details.push((uri, info));
+    }
+    let beds = embed_async(embedder, documents).await?;
+    let data = beds
+        .into_iter()
+        .zip(broad_details)
+        .map(|(bed, (uuid, info))| DetailedEmbedding { uuid, bed, info })
+        .collect::<Vec<_>>();
+    collection.upsert(data).await
+}
+
+/// Embeds every source of every article in the chunk in one batch, then regroups them by article
+async fn embed_multi(
+    collection: &MultiCollection,
+    embedder: Arc<dyn Embedder>,
+    junk: &JunkFilter,
+    chunk: Vec<ScrapedArticle>,
+) -> anyhow::Result<()> {
+    let mut documents = vec![];
+    let mut owners = vec![];
+    let mut data = vec![];
+    for (uuid, texts, info) in chunk
+        .into_iter()
+        .filter_map(|article| break_article_for_multidrant(article, collection.sources(), junk))
+    {
+        for (source, text) in texts {
+            documents.push(text);
+            owners.push((data.len(), source));
+        }
+        data.push(MultiEmbedding {
+            uuid,
+            beds: HashMap::new(),
+            info,
+        });
+    }
+    let beds = embed_async(embedder, documents).await?;
+    for (bed, (ix, source)) in beds.into_iter().zip(owners) {
+        data[ix].beds.insert(source, bed);
+    }
+    collection.upsert(data).await
+}
+
 async fn worker_thread(
-    collection: Arc<Collection>,
-    loaded_model: Arc<TextEmbedding>,
+    target: Arc<Target>,
+    embedder: Arc<dyn Embedder>,
+    junk: Arc<JunkFilter>,
     data: Arc<Mutex<Vec<NaiveDate>>>,
     tx: Sender<(String, String)>,
+    doc_stats: Arc<std::sync::Mutex<DocStats>>,
 ) {
     loop {
         let date = {
@@ -37,7 +116,7 @@ async fn worker_thread(
             println!("{} months left!", lock.len());
             date
         };
-        let scraped = match ScrapedJson::from_date(date) {
+        let mut docs = match ScrapedDocs::open(date) {
             Ok(val) => val,
             Err(e) => {
                 tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
@@ -46,28 +125,10 @@ async fn worker_thread(
                 break;
             }
         };
-        let mut articles = scraped.response.docs;
-        while !articles.is_empty() {
-            let chunk = articles
-                .drain(0..CHUNK_SIZE.min(articles.len()))
-                .map(|article| break_article_for_mydrant(article, BED_SOURCE))
-                .filter_map(|details| details);
-            let mut documents = vec![];
-            let mut broad_details = vec![];
-            for (uri, text, info) in chunk {
-                documents.push(text);
-                broad_details.push((uri, info));
-            }
-            let model_arc = loaded_model.clone();
-            let bed_hand = tokio::task::spawn_blocking(move || model_arc.embed(documents, None));
-            let beds = match bed_hand.await {
-                Ok(Ok(beds)) => beds,
-                Ok(Err(e)) => {
-                    tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
-                        .await
-                        .ok();
-                    break;
-                }
+        loop {
+            let chunk = match docs.next_chunk(CHUNK_SIZE).await {
+                Ok(chunk) if chunk.is_empty() => break,
+                Ok(chunk) => chunk,
                 Err(e) => {
                     tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
                         .await
@@ -75,38 +136,91 @@ async fn worker_thread(
                     break;
                 }
             };
-            let data = beds
-                .into_iter()
-                .zip(broad_details.into_iter())
-                .map(|(bed, (uuid, info))| DetailedEmbedding { uuid, bed, info })
-                .collect::<Vec<_>>();
-            if let Err(e) = collection.upsert(data).await {
+            let res = match target.as_ref() {
+                Target::Single(collection) => {
+                    embed_single(collection.as_ref(), embedder.clone(), &junk, chunk).await
+                }
+                Target::Multi(collection) => {
+                    embed_multi(collection, embedder.clone(), &junk, chunk).await
+                }
+            };
+            if let Err(e) = res {
                 tx.send((format!("{:?}", get_json_path(date)), format!("{:?}", e)))
                     .await
                     .ok();
                 break;
             };
         }
+        doc_stats.lock().unwrap().record(date, docs.stats());
     }
 }
 
 const NUM_WORKERS: u32 = 4;
 const CHUNK_SIZE: usize = 64;
 const BED_SOURCE: BedSource = BedSource::HeadlineMain;
-const BED_DIM: u64 = 1024;
+/// Set to `Some(...)` to write a single collection with one named vector per source instead of
+/// the usual one-source collection (`BED_SOURCE` is ignored in that case)
+const MULTI_SOURCES: Option<&[BedSource]> = None;
+/// How qdrant should store and search the collection, see `IndexConfig`
+const INDEX_CONFIG: IndexConfig = IndexConfig::DEFAULT;
+/// Set to `Some(path)` to build a local `FlatStore` file instead of a qdrant collection
+const LOCAL_STORE: Option<&str> = None;
 const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
 const DISTANCE: Distance = Distance::Cosine;
+/// Embeddings are cached here by text + model, so re-runs and new collections skip the model
+const BED_CACHE_DIR: &str = "bed_cache";
+/// Articles matching these never get embedded, see `JunkRules`
+const JUNK_RULES: JunkRules = JunkRules::DEFAULT;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
-    let collection = Arc::new(Collection::new(
-        BED_SOURCE, BED_DIM, BED_MODEL, DISTANCE, qdrant,
-    ));
-    collection.ensure_created().await?;
-    let loaded_model = Arc::new(TextEmbedding::try_new(
-        InitOptions::new(BED_MODEL).with_show_download_progress(true),
+    let cached = Arc::new(CachedEmbedder::try_new(
+        Box::new(FastEmbedder::try_new(BED_MODEL)?),
+        Path::new(BED_CACHE_DIR),
     )?);
+    let embedder: Arc<dyn Embedder> = cached.clone();
+    let junk = Arc::new(JunkFilter::try_new(JUNK_RULES)?);
+    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+    // Pass a version (or "new") to build a fresh copy next to the live one, see `collections`
+    let version = std::env::args()
+        .nth(1)
+        .map(|version| match version.as_str() {
+            "new" => Collection::new_version(),
+            _ => version,
+        });
+    if let Some(version) = version.as_ref() {
+        println!("Building version {version}");
+    }
+    let target = match MULTI_SOURCES {
+        Some(sources) => {
+            let mut collection =
+                MultiCollection::new(sources.to_vec(), embedder.as_ref(), DISTANCE, qdrant)
+                    .with_index_config(INDEX_CONFIG);
+            if let Some(version) = version.as_ref() {
+                collection = collection.with_version(version);
+            }
+            Target::Multi(Box::new(collection))
+        }
+        None => match LOCAL_STORE {
+            Some(path) => Target::Single(Box::new(FlatStore::open(
+                Path::new(path),
+                BED_SOURCE,
+                embedder.as_ref(),
+                DISTANCE,
+            )?)),
+            None => {
+                let mut collection =
+                    Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant)
+                        .with_index_config(INDEX_CONFIG);
+                if let Some(version) = version.as_ref() {
+                    collection = collection.with_version(version);
+                }
+                Target::Single(Box::new(collection))
+            }
+        },
+    };
+    let target = Arc::new(target);
+    target.ensure_created().await?;
 
     let mut raw_data = vec![];
     for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
@@ -118,18 +232,25 @@ async fn main() -> anyhow::Result<()> {
         }
     }
     let data = Arc::new(Mutex::new(raw_data));
+    let doc_stats = Arc::new(std::sync::Mutex::new(DocStats::default()));
     let mut set = JoinSet::new();
     let (tx, rx) = channel(64);
     set.spawn(error_thread(rx));
     for _ in 0..NUM_WORKERS {
         set.spawn(worker_thread(
-            collection.clone(),
-            loaded_model.clone(),
+            target.clone(),
+            embedder.clone(),
+            junk.clone(),
             data.clone(),
             tx.clone(),
+            doc_stats.clone(),
         ));
     }
     drop(tx); // If we don't drop this the error thread never dies...
-    while let Some(_) = set.join_next().await {}
+    while set.join_next().await.is_some() {}
+    doc_stats.lock().unwrap().report();
+    let (hits, misses) = cached.stats();
+    println!("Embedding cache: {hits} hits, {misses} misses");
+    junk.report();
     Ok(())
 }
@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+
+use crate::mydrant::{Collection, CommonInfo, DetailedEmbedding};
+
+/// Every vector for some slice of the archive, held in memory and searched by brute force.
+/// This is the ground truth that qdrant's approximate index gets graded against.
+pub struct ExactIndex {
+    infos: Vec<CommonInfo>,
+    /// Normalized, so a dot product is a cosine similarity
+    beds: Vec<Vec<f32>>,
+}
+impl ExactIndex {
+    pub fn new(data: Vec<DetailedEmbedding>) -> Self {
+        let mut infos = vec![];
+        let mut beds = vec![];
+        for details in data {
+            infos.push(details.info);
+            beds.push(normalize(details.bed));
+        }
+        Self { infos, beds }
+    }
+
+    /// Pulls everything published between `start_year` and `end_year` out of the collection
+    pub async fn from_collection(
+        collection: &Collection,
+        start_year: u32,
+        end_year: u32,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::new(
+            collection.export_years(start_year, end_year).await?,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.beds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.beds.is_empty()
+    }
+
+    /// The stored (normalized) vector at `ix`, handy for using the archive itself as queries
+    pub fn bed(&self, ix: usize) -> &[f32] {
+        &self.beds[ix]
+    }
+
+    /// The true `k` most cosine-similar points to `bed`, best first
+    pub fn top_k(&self, bed: &[f32], k: usize) -> Vec<(CommonInfo, f32)> {
+        let query = normalize(bed.to_vec());
+        let mut scored = self
+            .beds
+            .iter()
+            .enumerate()
+            .map(|(ix, other)| (ix, dot(&query, other)))
+            .collect::<Vec<_>>();
+        let k = k.min(scored.len());
+        if k == 0 {
+            return vec![];
+        }
+        // Partition first so we only fully sort the winners
+        scored.select_nth_unstable_by(k - 1, |a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored
+            .into_iter()
+            .map(|(ix, score)| (self.infos[ix].clone(), score))
+            .collect()
+    }
+}
+
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+pub(crate) fn normalize(mut bed: Vec<f32>) -> Vec<f32> {
+    let norm = dot(&bed, &bed).sqrt();
+    if norm > 0.0 {
+        bed.iter_mut().for_each(|x| *x /= norm);
+    }
+    bed
+}
+
+/// What fraction of the true top-k `found` managed to include
+pub fn recall_at_k(found: &[String], truth: &[String]) -> f64 {
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let truth = truth.iter().collect::<HashSet<_>>();
+    let hits = found.iter().filter(|uri| truth.contains(uri)).count();
+    hits as f64 / truth.len() as f64
+}
+
+#[derive(Debug, Clone)]
+pub struct RecallReport {
+    pub k: usize,
+    pub num_queries: usize,
+    pub mean: f64,
+    pub min: f64,
+    /// Fraction of queries where the index found every true neighbour
+    pub perfect: f64,
+}
+impl std::fmt::Display for RecallReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "recall@{} over {} queries: mean {:.4}, min {:.4}, perfect {:.1}%",
+            self.k,
+            self.num_queries,
+            self.mean,
+            self.min,
+            self.perfect * 100.0
+        )
+    }
+}
+
+/// Grades the qdrant index against brute force, using every `stride`th archive vector as a query.
+/// Both sides only see points published between `start_year` and `end_year`.
+pub async fn evaluate_recall(
+    collection: &Collection,
+    exact: &ExactIndex,
+    start_year: u32,
+    end_year: u32,
+    k: usize,
+    stride: usize,
+) -> anyhow::Result<RecallReport> {
+    let mut recalls = vec![];
+    for ix in (0..exact.len()).step_by(stride.max(1)) {
+        let bed = exact.bed(ix);
+        let truth = exact
+            .top_k(bed, k)
+            .into_iter()
+            .map(|(info, _)| info.uri)
+            .collect::<Vec<_>>();
+        let found = collection
+            .top_k_in_years(bed.to_vec(), k as u64, start_year, end_year)
+            .await?
+            .into_iter()
+            .map(|(info, _)| info.uri)
+            .collect::<Vec<_>>();
+        recalls.push(recall_at_k(&found, &truth));
+    }
+    if recalls.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Nothing between {start_year} and {end_year} to query with"
+        ));
+    }
+    Ok(RecallReport {
+        k,
+        num_queries: recalls.len(),
+        mean: recalls.iter().sum::<f64>() / recalls.len() as f64,
+        min: recalls.iter().cloned().fold(f64::MAX, f64::min),
+        perfect: recalls.iter().filter(|r| **r >= 1.0).count() as f64 / recalls.len() as f64,
+    })
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, FixedOffset};
+use reqwest::Client;
+
+use crate::{
+    nyt::clean_snippet,
+    source::{NewsArticle, NewsImage, NewsSource},
+};
+
+/// Any RSS (0.9x, 1.0, 2.0) or Atom feed. There's no archive, just whatever the feed has now.
+pub struct FeedSource {
+    name: String,
+    url: String,
+    client: Client,
+}
+impl FeedSource {
+    /// `name` gets stored with every article, so keep it stable (like "guardian-world")
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    /// `None` for entries that don't have enough to be matched (no headline, no link), or no
+    /// date. The date decides which day an article is filed under and when the ager archives it,
+    /// so there's no guessing one.
+    fn to_article(&self, entry: feed_rs::model::Entry) -> Option<NewsArticle> {
+        let headline = clean_snippet(entry.title?.content).trim().to_string();
+        let url = entry.links.first()?.href.clone();
+        if headline.is_empty() {
+            return None;
+        }
+        let published_at: DateTime<FixedOffset> = entry.published.or(entry.updated)?.fixed_offset();
+        let summary = entry
+            .summary
+            .map(|text| text.content)
+            .or(entry.content.and_then(|content| content.body))
+            .map(|html| clean_snippet(html).trim().to_string())
+            .unwrap_or_default();
+        let image = entry.media.iter().find_map(|media| {
+            let url = media
+                .content
+                .iter()
+                .find_map(|content| content.url.as_ref().map(|url| url.to_string()))
+                .or(media
+                    .thumbnails
+                    .first()
+                    .map(|thumbnail| thumbnail.image.uri.clone()))?;
+            Some(NewsImage {
+                url,
+                caption: media.description.as_ref().map(|text| text.content.clone()),
+                format: None,
+                type_: "image".to_string(),
+                subtype: None,
+            })
+        });
+        let tags = entry
+            .categories
+            .iter()
+            .map(|category| category.label.clone().unwrap_or(category.term.clone()))
+            .collect::<Vec<_>>();
+        Some(NewsArticle {
+            source: self.name.clone(),
+            // Guids are optional (feed-rs hashes something up without one), links aren't
+            uri: url.clone(),
+            url,
+            headline,
+            summary,
+            published_at,
+            section: tags.first().cloned().unwrap_or_default(),
+            subsection: String::new(),
+            kind: String::new(),
+            kicker: String::new(),
+            type_of_material: String::new(),
+            tags,
+            image,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl NewsSource for FeedSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn latest(&self) -> anyhow::Result<Vec<NewsArticle>> {
+        let body = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let feed = feed_rs::parser::parse(body.as_ref())
+            .map_err(|e| anyhow::anyhow!("{} isn't a feed we can read: {e}", self.url))?;
+        Ok(feed
+            .entries
+            .into_iter()
+            .filter_map(|entry| self.to_article(entry))
+            .collect())
+    }
+}
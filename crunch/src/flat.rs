@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+};
+
+use qdrant_client::qdrant::Distance;
+use uuid::Uuid;
+
+use crate::{
+    embed::Embedder,
+    exact::{dot, normalize},
+    mydrant::{base_collection_name, BedSource, Candidate, CommonInfo, DetailedEmbedding},
+    nyt::uri_to_uuid,
+    portable::{read_header, read_point, write_header, write_point, PortableHeader},
+    store::VectorStore,
+};
+
+/// Whether reading stopped because the file ran out partway through a record
+fn is_torn(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::UnexpectedEof)
+}
+
+struct FlatPoints {
+    ids: HashMap<Uuid, usize>,
+    infos: Vec<CommonInfo>,
+    /// Normalized when using cosine distance, so scoring is always a dot product
+    beds: Vec<Vec<f32>>,
+}
+
+/// A brute-force index that lives in memory and persists to a single local file (in the
+/// `portable` format, so `collections import` can load it into qdrant later).
+///
+/// The file is an append-only log: upserts and payload overwrites are appended, and the latest
+/// record for a uri wins when loading. Use `compact` to squash it back down.
+pub struct FlatStore {
+    path: PathBuf,
+    header: PortableHeader,
+    distance: Distance,
+    points: RwLock<FlatPoints>,
+    log: Mutex<BufWriter<File>>,
+}
+impl FlatStore {
+    /// Loads the index at `path`, starting an empty one if there's nothing there yet. A partial
+    /// record at the end (from a crash mid-append) is cut off with a warning.
+    pub fn open(
+        path: &Path,
+        source: BedSource,
+        embedder: &dyn Embedder,
+        distance: Distance,
+    ) -> anyhow::Result<Self> {
+        if distance != Distance::Cosine && distance != Distance::Dot {
+            return Err(anyhow::anyhow!(
+                "FlatStore only does Cosine and Dot, not {:?}",
+                distance
+            ));
+        }
+        let header = PortableHeader {
+            collection: base_collection_name(source, &embedder.model_id(), distance),
+            dim: embedder.dim(),
+        };
+        let mut points = FlatPoints {
+            ids: HashMap::new(),
+            infos: vec![],
+            beds: vec![],
+        };
+        let mut num_records = 0;
+        if path.exists() {
+            let mut input = BufReader::new(File::open(path)?);
+            let existing = read_header(&mut input, path)?;
+            if existing.collection != header.collection || existing.dim != header.dim {
+                return Err(anyhow::anyhow!(
+                    "{:?} holds {} ({} dims), expected {} ({} dims)",
+                    path,
+                    existing.collection,
+                    existing.dim,
+                    header.collection,
+                    header.dim
+                ));
+            }
+            let mut complete_len = input.stream_position()?;
+            loop {
+                match read_point(&mut input, header.dim) {
+                    Ok(Some((info, bed))) => {
+                        Self::insert(&mut points, distance, info, bed);
+                        num_records += 1;
+                        complete_len = input.stream_position()?;
+                    }
+                    Ok(None) => break,
+                    Err(e) if is_torn(&e) => {
+                        let len = std::fs::metadata(path)?.len();
+                        println!(
+                            "{:?} ends in a partial record, dropping its last {} bytes",
+                            path,
+                            len - complete_len
+                        );
+                        OpenOptions::new()
+                            .write(true)
+                            .open(path)?
+                            .set_len(complete_len)?;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        } else {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_header(&mut out, &header)?;
+            out.flush()?;
+        }
+        let log = BufWriter::new(OpenOptions::new().append(true).open(path)?);
+        let store = Self {
+            path: path.to_path_buf(),
+            header,
+            distance,
+            points: RwLock::new(points),
+            log: Mutex::new(log),
+        };
+        // Mostly overwritten records isn't worth carrying around
+        if num_records > 2 * store.len() {
+            store.compact()?;
+        }
+        Ok(store)
+    }
+
+    fn insert(points: &mut FlatPoints, distance: Distance, info: CommonInfo, bed: Vec<f32>) {
+        let bed = match distance {
+            Distance::Cosine => normalize(bed),
+            _ => bed,
+        };
+        let uuid = uri_to_uuid(&info.uri);
+        match points.ids.get(&uuid) {
+            Some(ix) => {
+                points.infos[*ix] = info;
+                points.beds[*ix] = bed;
+            }
+            None => {
+                points.ids.insert(uuid, points.beds.len());
+                points.infos.push(info);
+                points.beds.push(bed);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.read().unwrap().beds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Rewrites the file with only the latest record for each point
+    pub fn compact(&self) -> anyhow::Result<()> {
+        let points = self.points.read().unwrap();
+        let mut log = self.log.lock().unwrap();
+        log.flush()?;
+        let tmp_path = self.path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        write_header(&mut out, &self.header)?;
+        for (info, bed) in points.infos.iter().zip(points.beds.iter()) {
+            write_point(&mut out, info, bed)?;
+        }
+        out.flush()?;
+        drop(out);
+        std::fs::rename(&tmp_path, &self.path)?;
+        *log = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        Ok(())
+    }
+
+    fn scored(&self, bed: &[f32], k: u64) -> anyhow::Result<Vec<(usize, f32)>> {
+        if bed.len() as u64 != self.header.dim {
+            return Err(anyhow::anyhow!(
+                "bed is not the right size, got {}, expected {}",
+                bed.len(),
+                self.header.dim
+            ));
+        }
+        let query = match self.distance {
+            Distance::Cosine => normalize(bed.to_vec()),
+            _ => bed.to_vec(),
+        };
+        let points = self.points.read().unwrap();
+        let mut scored = points
+            .beds
+            .iter()
+            .enumerate()
+            .map(|(ix, other)| (ix, dot(&query, other)))
+            .collect::<Vec<_>>();
+        let k = (k as usize).min(scored.len());
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        scored.select_nth_unstable_by(k - 1, |a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored)
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorStore for FlatStore {
+    async fn ensure_created(&self) -> anyhow::Result<()> {
+        // The file is made in `open`
+        Ok(())
+    }
+
+    /// All or nothing: the whole batch is checked before any of it is written
+    async fn upsert(&self, data: Vec<DetailedEmbedding>) -> anyhow::Result<()> {
+        for details in data.iter() {
+            if details.bed.len() as u64 != self.header.dim {
+                return Err(anyhow::anyhow!(
+                    "bed for {} is not the right size, got {}, expected {}",
+                    details.info.uri,
+                    details.bed.len(),
+                    self.header.dim
+                ));
+            }
+            // Points are keyed by uri here (the log doesn't store ids), so a different id
+            // would quietly end up under the wrong one
+            if details.uuid != uri_to_uuid(&details.info.uri) {
+                return Err(anyhow::anyhow!(
+                    "point {} doesn't match its uri {} (expected {})",
+                    details.uuid,
+                    details.info.uri,
+                    uri_to_uuid(&details.info.uri)
+                ));
+            }
+        }
+        let mut points = self.points.write().unwrap();
+        let mut log = self.log.lock().unwrap();
+        for details in data {
+            write_point(&mut *log, &details.info, &details.bed)?;
+            Self::insert(&mut points, self.distance, details.info, details.bed);
+        }
+        log.flush()?;
+        Ok(())
+    }
+
+    async fn overwrite_payload(&self, uuid: Uuid, info: CommonInfo) -> anyhow::Result<()> {
+        let mut points = self.points.write().unwrap();
+        let Some(ix) = points.ids.get(&uuid).copied() else {
+            return Err(anyhow::anyhow!("No point {} in {:?}", uuid, self.path));
+        };
+        let mut log = self.log.lock().unwrap();
+        write_point(&mut *log, &info, &points.beds[ix])?;
+        log.flush()?;
+        points.infos[ix] = info;
+        Ok(())
+    }
+
+    async fn top_k(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        let scored = self.scored(&bed, k)?;
+        let points = self.points.read().unwrap();
+        Ok(scored
+            .into_iter()
+            .map(|(ix, score)| (points.infos[ix].clone(), score))
+            .collect())
+    }
+
+    async fn top_k_candidates(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<Candidate>> {
+        let scored = self.scored(&bed, k)?;
+        let points = self.points.read().unwrap();
+        Ok(scored
+            .into_iter()
+            .map(|(ix, score)| Candidate {
+                info: points.infos[ix].clone(),
+                score,
+                rerank_score: None,
+                bed: points.beds[ix].clone(),
+            })
+            .collect())
+    }
+}
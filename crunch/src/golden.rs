@@ -0,0 +1,112 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::exact::recall_at_k;
+
+/// An archive article that a person decided is a good match for a golden query
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GoldenMatch {
+    pub uri: String,
+    /// How good of a match it is. 0 = not relevant, 1 = related, 2 = good, 3 = history rhyming perfectly.
+    pub grade: u32,
+}
+
+/// One line of a golden set file. Golden sets are JSONL, e.g:
+/// `{"query": "Markets tumble as banks fail", "relevant": [{"uri": "nyt://article/...", "grade": 3}]}`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GoldenQuery {
+    /// A contemporary headline
+    pub query: String,
+    pub relevant: Vec<GoldenMatch>,
+}
+impl GoldenQuery {
+    fn grades(&self) -> HashMap<&str, u32> {
+        self.relevant
+            .iter()
+            .map(|m| (m.uri.as_str(), m.grade))
+            .collect()
+    }
+}
+
+pub fn load_golden_set(path: &Path) -> anyhow::Result<Vec<GoldenQuery>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut queries = vec![];
+    for (ix, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let query = serde_json::from_str::<GoldenQuery>(line)
+            .map_err(|e| anyhow::anyhow!("{:?} line {}: {}", path, ix + 1, e))?;
+        queries.push(query);
+    }
+    Ok(queries)
+}
+
+fn dcg(grades: impl Iterator<Item = u32>) -> f64 {
+    grades
+        .enumerate()
+        .map(|(rank, grade)| (2f64.powi(grade as i32) - 1.0) / (rank as f64 + 2.0).log2())
+        .sum()
+}
+
+/// Normalized discounted cumulative gain of the first `k` results
+pub fn ndcg_at_k(ranked: &[String], query: &GoldenQuery, k: usize) -> f64 {
+    let grades = query.grades();
+    let actual = dcg(ranked
+        .iter()
+        .take(k)
+        .map(|uri| grades.get(uri.as_str()).copied().unwrap_or(0)));
+    let mut ideal_grades = query.relevant.iter().map(|m| m.grade).collect::<Vec<_>>();
+    ideal_grades.sort_by(|a, b| b.cmp(a));
+    let ideal = dcg(ideal_grades.into_iter().take(k));
+    if ideal == 0.0 {
+        return 0.0;
+    }
+    actual / ideal
+}
+
+/// 1 / the rank of the first relevant result, or 0 if there isn't one
+pub fn reciprocal_rank(ranked: &[String], query: &GoldenQuery) -> f64 {
+    let grades = query.grades();
+    ranked
+        .iter()
+        .position(|uri| grades.get(uri.as_str()).copied().unwrap_or(0) > 0)
+        .map(|ix| 1.0 / (ix as f64 + 1.0))
+        .unwrap_or(0.0)
+}
+
+/// Mean metrics for one retriever over a whole golden set
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BenchMetrics {
+    pub k: usize,
+    pub num_queries: usize,
+    pub ndcg: f64,
+    pub mrr: f64,
+    pub recall: f64,
+}
+
+/// `ranked` holds the retriever's results (best first) for each query, in the same order as `golden`
+pub fn score_golden_set(golden: &[GoldenQuery], ranked: &[Vec<String>], k: usize) -> BenchMetrics {
+    let mut ndcg = 0.0;
+    let mut mrr = 0.0;
+    let mut recall = 0.0;
+    for (query, results) in golden.iter().zip(ranked.iter()) {
+        let relevant = query
+            .relevant
+            .iter()
+            .filter(|m| m.grade > 0)
+            .map(|m| m.uri.clone())
+            .collect::<Vec<_>>();
+        let top = &results[..k.min(results.len())];
+        ndcg += ndcg_at_k(results, query, k);
+        mrr += reciprocal_rank(top, query);
+        recall += recall_at_k(top, &relevant);
+    }
+    let n = golden.len().max(1) as f64;
+    BenchMetrics {
+        k,
+        num_queries: golden.len(),
+        ndcg: ndcg / n,
+        mrr: mrr / n,
+        recall: recall / n,
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{nyt::ScrapedArticle, source::NewsArticle};
+
+/// What makes an article not worth embedding or matching against. Everything is compared
+/// case-insensitively, and the lists are `&'static` so each binary can keep its rules in a const.
+#[derive(Debug, Clone, Copy)]
+pub struct JunkRules {
+    pub type_of_material: &'static [&'static str],
+    pub document_type: &'static [&'static str],
+    pub news_desk: &'static [&'static str],
+    /// Regexes matched against the headline
+    pub headline_patterns: &'static [&'static str],
+    /// Headlines shorter than this (in chars, after trimming) are too vague to match on
+    pub min_headline_len: usize,
+}
+impl JunkRules {
+    /// Lets everything through
+    pub const OFF: Self = Self {
+        type_of_material: &[],
+        document_type: &[],
+        news_desk: &[],
+        headline_patterns: &[],
+        min_headline_len: 0,
+    };
+
+    /// The boilerplate that kept showing up in `top_k` results
+    pub const DEFAULT: Self = Self {
+        type_of_material: &[
+            "Paid Death Notice",
+            "Correction",
+            "Summary",
+            "List",
+            "Schedule",
+            "Statistics",
+            "Results Listing",
+            "Classified",
+        ],
+        document_type: &["paidpost"],
+        news_desk: &["Classified", "Paid Death Notices"],
+        headline_patterns: &[
+            r"^no headline",
+            r"^paid notice",
+            r"^corrections?\b",
+            r"^results plus",
+            r"^transactions$",
+            r"^(sports|business|metro) people",
+            r"^(inside|index)$",
+        ],
+        min_headline_len: 12,
+    };
+}
+
+/// Why an article got filtered out
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JunkReason {
+    TypeOfMaterial(String),
+    DocumentType(String),
+    NewsDesk(String),
+    /// Holds the pattern that matched
+    Headline(String),
+    TooShort,
+}
+impl std::fmt::Display for JunkReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JunkReason::TypeOfMaterial(value) => write!(f, "type_of_material = {value}"),
+            JunkReason::DocumentType(value) => write!(f, "document_type = {value}"),
+            JunkReason::NewsDesk(value) => write!(f, "news_desk = {value}"),
+            JunkReason::Headline(pattern) => write!(f, "headline matches /{pattern}/"),
+            JunkReason::TooShort => write!(f, "headline too short"),
+        }
+    }
+}
+
+/// `JunkRules` with the regexes compiled, keeping count of everything it excludes.
+/// Safe to share between worker threads.
+pub struct JunkFilter {
+    rules: JunkRules,
+    patterns: Vec<(&'static str, Regex)>,
+    counts: Mutex<HashMap<JunkReason, u64>>,
+}
+impl JunkFilter {
+    pub fn try_new(rules: JunkRules) -> anyhow::Result<Self> {
+        let patterns = rules
+            .headline_patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|regex| (*pattern, regex))
+                    .map_err(|e| anyhow::anyhow!("Bad junk pattern {pattern}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            patterns,
+            counts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Checks the fields the rules care about, counting the article if it's junk
+    pub fn check(
+        &self,
+        headline: &str,
+        type_of_material: &str,
+        document_type: &str,
+        news_desk: &str,
+    ) -> Option<JunkReason> {
+        let listed = |list: &[&str], value: &str| {
+            let value = value.trim();
+            list.iter().any(|item| item.eq_ignore_ascii_case(value))
+        };
+        let headline = headline.trim();
+        let reason = if listed(self.rules.type_of_material, type_of_material) {
+            Some(JunkReason::TypeOfMaterial(
+                type_of_material.trim().to_string(),
+            ))
+        } else if listed(self.rules.document_type, document_type) {
+            Some(JunkReason::DocumentType(document_type.trim().to_string()))
+        } else if listed(self.rules.news_desk, news_desk) {
+            Some(JunkReason::NewsDesk(news_desk.trim().to_string()))
+        } else if let Some((pattern, _)) = self.patterns.iter().find(|(_, r)| r.is_match(headline))
+        {
+            Some(JunkReason::Headline(pattern.to_string()))
+        } else if headline.chars().count() < self.rules.min_headline_len {
+            Some(JunkReason::TooShort)
+        } else {
+            None
+        };
+        if let Some(reason) = reason.as_ref() {
+            *self
+                .counts
+                .lock()
+                .unwrap()
+                .entry(reason.clone())
+                .or_insert(0) += 1;
+        }
+        reason
+    }
+
+    pub fn check_scraped(&self, article: &ScrapedArticle) -> Option<JunkReason> {
+        self.check(
+            &article.headline.main,
+            &article.type_of_material,
+            &article.document_type,
+            &article.news_desk,
+        )
+    }
+
+    /// `kind` stands in for `document_type` and `section` for `news_desk`. The `type_of_material`
+    /// rules only fire when the source fills it in (the NYT sometimes does, feeds never), so
+    /// for feeds it's down to the headline rules.
+    pub fn check_news(&self, article: &NewsArticle) -> Option<JunkReason> {
+        self.check(
+            &article.headline,
+            &article.type_of_material,
+            &article.kind,
+            &article.section,
+        )
+    }
+
+    /// Everything excluded so far, most common reason first
+    pub fn counts(&self) -> Vec<(JunkReason, u64)> {
+        let mut counts = self
+            .counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(reason, count)| (reason.clone(), *count))
+            .collect::<Vec<_>>();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+
+    /// Prints what was excluded and why
+    pub fn report(&self) {
+        let counts = self.counts();
+        let total = counts.iter().map(|(_, count)| count).sum::<u64>();
+        println!("Excluded {total} junk articles");
+        for (reason, count) in counts {
+            println!("  {count:>8}  {reason}");
+        }
+    }
+}
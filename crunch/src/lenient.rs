@@ -0,0 +1,184 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{get_json_path, manifest::write_atomic, nyt::ScrapedArticle};
+
+/// Docs that can't be read even after coercion end up here, one jsonl file per month
+pub const QUARANTINE_DIR: &str = "scrapes/quarantine";
+
+/// How a month's docs went
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DocStats {
+    /// Read as-is
+    pub parsed: usize,
+    /// Read after filling in missing or null fields
+    pub coerced: usize,
+    /// Unreadable either way, and quarantined
+    pub skipped: usize,
+}
+impl DocStats {
+    pub fn add(&mut self, other: DocStats) {
+        self.parsed += other.parsed;
+        self.coerced += other.coerced;
+        self.skipped += other.skipped;
+    }
+
+    /// Folds in a finished month, mentioning it if anything was off
+    pub fn record(&mut self, date: NaiveDate, month: DocStats) {
+        if month.coerced > 0 || month.skipped > 0 {
+            println!(
+                "{:?}: coerced {}, skipped {}",
+                get_json_path(date),
+                month.coerced,
+                month.skipped
+            );
+        }
+        self.add(month);
+    }
+
+    pub fn report(&self) {
+        println!(
+            "Read {} docs as-is, {} after coercion, skipped {} (see {QUARANTINE_DIR})",
+            self.parsed, self.coerced, self.skipped
+        );
+    }
+}
+
+pub enum DocOutcome {
+    Parsed(ScrapedArticle),
+    Coerced(ScrapedArticle),
+    Skipped(String),
+}
+
+/// Reads one doc, falling back to `coerce_article` if the strict schema rejects it
+pub fn decode_article(doc: &Value) -> DocOutcome {
+    // Most docs are fine, so try them as they are first
+    if let Ok(article) = ScrapedArticle::deserialize(doc) {
+        return DocOutcome::Parsed(article);
+    }
+    let mut doc = doc.clone();
+    coerce_article(&mut doc);
+    match ScrapedArticle::deserialize(&doc) {
+        Ok(article) => DocOutcome::Coerced(article),
+        Err(e) => DocOutcome::Skipped(e.to_string()),
+    }
+}
+
+/// Sets `key` to `default` if it's missing or null
+fn fill(obj: &mut Map<String, Value>, key: &str, default: Value) {
+    match obj.get(key) {
+        Some(value) if !value.is_null() => {}
+        _ => {
+            obj.insert(key.to_string(), default);
+        }
+    }
+}
+
+fn fill_strings(obj: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        fill(obj, key, json!(""));
+    }
+}
+
+fn fill_numbers(obj: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        fill(obj, key, json!(0));
+    }
+}
+
+/// Applies `f` to every object in the array at `key`
+fn each_object(obj: &mut Map<String, Value>, key: &str, f: impl Fn(&mut Map<String, Value>)) {
+    if let Some(Value::Array(items)) = obj.get_mut(key) {
+        for item in items.iter_mut() {
+            if let Value::Object(item) = item {
+                f(item);
+            }
+        }
+    }
+}
+
+/// Fills in the fields older months tend to leave out or null. The ones we can't make up
+/// (`uri`, `pub_date`, `headline.main`) are left alone, so those docs still get skipped.
+fn coerce_article(doc: &mut Value) {
+    let Value::Object(obj) = doc else {
+        return;
+    };
+    fill_strings(
+        obj,
+        &[
+            "web_url",
+            "snippet",
+            "source",
+            "document_type",
+            "news_desk",
+            "section_name",
+            "type_of_material",
+        ],
+    );
+    fill(obj, "multimedia", json!([]));
+    fill(obj, "keywords", json!([]));
+    fill(obj, "byline", json!({}));
+
+    if let Some(Value::Object(headline)) = obj.get_mut("headline") {
+        fill_strings(headline, &["print_headline"]);
+    }
+    if let Some(Value::Object(byline)) = obj.get_mut("byline") {
+        fill_strings(byline, &["original"]);
+        fill(byline, "person", json!([]));
+        each_object(byline, "person", |person| {
+            fill_strings(person, &["firstname", "lastname", "role", "organization"]);
+            fill_numbers(person, &["rank"]);
+        });
+    }
+    each_object(obj, "multimedia", |media| {
+        fill_strings(media, &["subtype", "type", "url", "crop_name"]);
+        fill_numbers(media, &["rank", "height", "width"]);
+        fill(media, "legacy", json!({}));
+    });
+    each_object(obj, "keywords", |keyword| {
+        fill_strings(keyword, &["name", "value", "major"]);
+        fill_numbers(keyword, &["rank"]);
+    });
+}
+
+/// Collects a month's unreadable docs for `QUARANTINE_DIR/{month}.jsonl`. Nothing's written
+/// until `finish`, so the file always matches the latest complete read of the month.
+pub struct Quarantine {
+    path: PathBuf,
+    /// jsonl, there's never more than a handful
+    lines: Vec<u8>,
+}
+impl Quarantine {
+    pub fn for_month(date: NaiveDate) -> Self {
+        let name = get_json_path(date).with_extension("jsonl");
+        Self {
+            path: Path::new(QUARANTINE_DIR).join(name.file_name().unwrap()),
+            lines: vec![],
+        }
+    }
+
+    pub fn add(&mut self, doc: &Value, error: &str) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.lines, &json!({ "error": error, "doc": doc }))?;
+        self.lines.push(b'\n');
+        Ok(())
+    }
+
+    /// Call once every doc has been read. Replaces the month's file, or removes it if nothing
+    /// was skipped this time (say after a coercion fix).
+    pub fn finish(self) -> anyhow::Result<()> {
+        if self.lines.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        std::fs::create_dir_all(QUARANTINE_DIR)?;
+        write_atomic(&self.path, &self.lines)
+    }
+}
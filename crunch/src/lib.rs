@@ -2,9 +2,32 @@ use std::path::{Path, PathBuf};
 
 use chrono::{Datelike, NaiveDate};
 
+pub mod archive;
+pub mod compress;
+pub mod dedup;
+pub mod diversify;
+pub mod embed;
+pub mod exact;
+pub mod feed;
+pub mod flat;
+pub mod golden;
+pub mod junk;
+pub mod lenient;
+pub mod manifest;
+pub mod mock;
+pub mod multidrant;
 pub mod mydrant;
 pub mod nyt;
 pub mod pg;
+pub mod portable;
+pub mod repo;
+pub mod rerank;
+pub mod retry;
+pub mod search;
+pub mod source;
+pub mod sqlite;
+pub mod store;
+pub mod threshold;
 
 pub const START_YEAR: u32 = 1980;
 pub const END_YEAR: u32 = 2010; // inclusive
@@ -13,6 +36,7 @@ pub fn get_date(year: u32, month: u32) -> NaiveDate {
     NaiveDate::from_ymd_opt(year as i32, month, 1).unwrap()
 }
 
+/// Where the plain json for a month goes. It may be stored compressed instead, see `compress`.
 pub fn get_json_path(date: NaiveDate) -> PathBuf {
     Path::new("scrapes").join(format!("{}_{}.json", date.year_ce().1, date.month0() + 1))
 }
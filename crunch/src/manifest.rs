@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    compress::{find_scrape, read_scrape},
+    get_json_path,
+    nyt::ScrapedMeta,
+};
+
+/// Lives next to the scrapes it describes
+pub const MANIFEST_PATH: &str = "scrapes/manifest.json";
+
+/// What we knew about a month's file when we wrote it. Sizes and hashes are of the json, so
+/// they hold whether the file is stored compressed or not.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// `None` for files that were scraped before there was a manifest and adopted by `verify`
+    pub status: Option<u16>,
+    pub bytes: u64,
+    pub sha256: String,
+    /// `meta.hits`, what the API says the month has
+    pub hits: u32,
+    /// `docs.len()`, what we actually got
+    pub docs: usize,
+    pub fetched_at: String,
+}
+impl ManifestEntry {
+    /// Checks `body` is a month of articles and describes it
+    pub fn from_body(body: &[u8], status: Option<u16>) -> anyhow::Result<Self> {
+        let scraped: MonthShape = serde_json::from_slice(body)?;
+        Ok(Self {
+            status,
+            bytes: body.len() as u64,
+            sha256: sha256_hex(body),
+            hits: scraped.response.meta.hits,
+            docs: scraped.response.docs.len(),
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+/// Just enough of a scrape to check it's a month of articles. The docs themselves are decoded
+/// leniently later (see `lenient`), so one odd doc shouldn't make us refetch the month.
+#[derive(serde::Deserialize)]
+struct MonthShape {
+    response: ResponseShape,
+}
+
+#[derive(serde::Deserialize)]
+struct ResponseShape {
+    meta: ScrapedMeta,
+    docs: Vec<serde::de::IgnoredAny>,
+}
+
+/// Scrape file name (like `1980_1.json`) -> entry
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub months: BTreeMap<String, ManifestEntry>,
+}
+impl Manifest {
+    /// Empty if there's no manifest yet
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Path::new(MANIFEST_PATH);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        write_atomic(Path::new(MANIFEST_PATH), &serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn get(&self, date: NaiveDate) -> Option<&ManifestEntry> {
+        self.months.get(&month_key(date))
+    }
+
+    pub fn insert(&mut self, date: NaiveDate, entry: ManifestEntry) {
+        self.months.insert(month_key(date), entry);
+    }
+
+    pub fn remove(&mut self, date: NaiveDate) {
+        self.months.remove(&month_key(date));
+    }
+}
+
+fn month_key(date: NaiveDate) -> String {
+    get_json_path(date)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Writes to a temp file next to `path` and renames it over, so `path` is either the old
+/// contents or the new ones, never half of either
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    // Per process, in case two are writing the same file
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = PathBuf::from(tmp_name);
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Something wrong with a month, as found by `verify_month`
+#[derive(Debug)]
+pub enum MonthProblem {
+    /// No file (the scraper hasn't got to it)
+    Missing,
+    /// A file that loads fine but isn't in the manifest
+    Unlisted(ManifestEntry),
+    /// In the manifest but the file is gone
+    MissingFile,
+    WrongSize {
+        expected: u64,
+        actual: u64,
+    },
+    WrongHash,
+    Unparseable(String),
+    /// The API said there were more articles than it gave us
+    Short {
+        hits: u32,
+        docs: usize,
+    },
+}
+impl MonthProblem {
+    /// Whether the file should be thrown away and scraped again
+    pub fn is_corrupt(&self) -> bool {
+        matches!(
+            self,
+            MonthProblem::WrongSize { .. } | MonthProblem::WrongHash | MonthProblem::Unparseable(_)
+        )
+    }
+}
+impl Display for MonthProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonthProblem::Missing => write!(f, "not scraped"),
+            MonthProblem::Unlisted(_) => write!(f, "not in the manifest"),
+            MonthProblem::MissingFile => write!(f, "in the manifest but the file is gone"),
+            MonthProblem::WrongSize { expected, actual } => {
+                write!(f, "expected {expected} bytes, got {actual}")
+            }
+            MonthProblem::WrongHash => write!(f, "sha256 doesn't match the manifest"),
+            MonthProblem::Unparseable(e) => write!(f, "doesn't parse: {e}"),
+            MonthProblem::Short { hits, docs } => write!(f, "{hits} hits but {docs} docs"),
+        }
+    }
+}
+
+/// Rechecks a month's file against its manifest entry
+pub fn verify_month(manifest: &Manifest, date: NaiveDate) -> anyhow::Result<Option<MonthProblem>> {
+    let entry = manifest.get(date);
+    if find_scrape(date).is_none() {
+        return Ok(Some(match entry {
+            Some(_) => MonthProblem::MissingFile,
+            None => MonthProblem::Missing,
+        }));
+    }
+    let body = match read_scrape(date) {
+        Ok(body) => body,
+        Err(e) => return Ok(Some(MonthProblem::Unparseable(e.to_string()))),
+    };
+    let Some(entry) = entry else {
+        return Ok(Some(match ManifestEntry::from_body(&body, None) {
+            Ok(entry) => MonthProblem::Unlisted(entry),
+            Err(e) => MonthProblem::Unparseable(e.to_string()),
+        }));
+    };
+    if body.len() as u64 != entry.bytes {
+        return Ok(Some(MonthProblem::WrongSize {
+            expected: entry.bytes,
+            actual: body.len() as u64,
+        }));
+    }
+    if sha256_hex(&body) != entry.sha256 {
+        return Ok(Some(MonthProblem::WrongHash));
+    }
+    let fresh = match ManifestEntry::from_body(&body, entry.status) {
+        Ok(fresh) => fresh,
+        Err(e) => return Ok(Some(MonthProblem::Unparseable(e.to_string()))),
+    };
+    if (fresh.hits as usize) > fresh.docs {
+        return Ok(Some(MonthProblem::Short {
+            hits: fresh.hits,
+            docs: fresh.docs,
+        }));
+    }
+    Ok(None)
+}
@@ -0,0 +1,286 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Datelike, NaiveDate};
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use crate::compress::read_scrape;
+
+/// Served for top stories
+const CURRENT_FIXTURE: &str = "example_current.json";
+/// How many made-up articles a month gets when there's no real scrape of it on disk
+const DOCS_PER_MONTH: u32 = 20;
+/// This api key gets a 401, to exercise the "key rejected" path
+const BAD_KEY: &str = "bad";
+
+/// What to break, and how often. Counting rather than random so runs are repeatable.
+#[derive(Debug, Default)]
+pub struct Faults {
+    /// Every Nth request gets a 429 with `Retry-After: 1`
+    pub rate_limit_every: Option<u64>,
+    /// Every Nth request gets a 503
+    pub server_error_every: Option<u64>,
+    /// Every Nth request gets a truncated body
+    pub malformed_every: Option<u64>,
+    /// Before every response
+    pub delay: Option<Duration>,
+}
+impl Faults {
+    /// `--429 N --500 N --malformed N --slow MS`, see the `mock_nyt` bin
+    pub fn from_args(args: &[String]) -> anyhow::Result<Self> {
+        let mut faults = Faults::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or(anyhow::anyhow!("{flag} needs a value"))?
+                .parse::<u64>()?;
+            match flag.as_str() {
+                "--429" => faults.rate_limit_every = Some(value),
+                "--500" => faults.server_error_every = Some(value),
+                "--malformed" => faults.malformed_every = Some(value),
+                "--slow" => faults.delay = Some(Duration::from_millis(value)),
+                _ => return Err(anyhow::anyhow!("Unknown flag {flag}")),
+            }
+        }
+        Ok(faults)
+    }
+}
+
+struct MockState {
+    faults: Faults,
+    requests: AtomicU64,
+}
+
+#[derive(serde::Deserialize)]
+struct KeyParams {
+    #[serde(rename = "api-key")]
+    api_key: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct SearchParams {
+    #[serde(rename = "api-key")]
+    api_key: Option<String>,
+    begin_date: String,
+    end_date: String,
+    #[serde(default)]
+    page: usize,
+}
+
+fn every(n: Option<u64>, count: u64) -> bool {
+    matches!(n, Some(n) if n > 0 && count.is_multiple_of(n))
+}
+
+/// Everything that isn't the actual body: auth, delays and injected failures. `Err` is the
+/// response to send instead.
+async fn gate(state: &MockState, api_key: Option<&str>) -> Result<bool, Response> {
+    let count = state.requests.fetch_add(1, Ordering::Relaxed) + 1;
+    if let Some(delay) = state.faults.delay {
+        tokio::time::sleep(delay).await;
+    }
+    match api_key {
+        None | Some("") => return Err((StatusCode::UNAUTHORIZED, "no api-key").into_response()),
+        Some(BAD_KEY) => return Err((StatusCode::UNAUTHORIZED, "bad api-key").into_response()),
+        Some(_) => {}
+    }
+    if every(state.faults.rate_limit_every, count) {
+        println!("#{count}: 429");
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, "1")],
+            "slow down",
+        )
+            .into_response());
+    }
+    if every(state.faults.server_error_every, count) {
+        println!("#{count}: 503");
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "try again").into_response());
+    }
+    let malformed = every(state.faults.malformed_every, count);
+    if malformed {
+        println!("#{count}: malformed");
+    }
+    Ok(malformed)
+}
+
+/// Cuts the body off halfway, like a dropped connection would
+fn serve_json(body: Vec<u8>, malformed: bool) -> Response {
+    let body = match malformed {
+        true => body[..body.len() / 2].to_vec(),
+        false => body,
+    };
+    (StatusCode::OK, [("content-type", "application/json")], body).into_response()
+}
+
+/// A month of plausible-looking archive articles
+fn made_up_month(date: NaiveDate) -> serde_json::Value {
+    let (year, month) = (date.year(), date.month());
+    let docs = (0..DOCS_PER_MONTH)
+        .map(|i| {
+            let day = i % 28 + 1;
+            json!({
+                "web_url": format!("https://www.nytimes.com/{year}/{month:02}/{day:02}/mock/{i}.html"),
+                "snippet": format!("Mock snippet {i} for {year}/{month}."),
+                "print_page": "1",
+                "print_section": "A",
+                "source": "The New York Times",
+                "multimedia": [],
+                "headline": {
+                    "main": format!("Mock Headline {i} for {year}/{month}"),
+                    "kicker": null,
+                    "content_kicker": null,
+                    "print_headline": format!("Mock Headline {i}"),
+                    "name": null,
+                    "seo": null,
+                    "sub": null
+                },
+                "keywords": [],
+                "pub_date": format!("{year:04}-{month:02}-{day:02}T05:00:00+00:00"),
+                "document_type": "article",
+                "news_desk": "Mock",
+                "section_name": "Mock",
+                "byline": { "original": "By Mock Reporter", "person": [], "organization": null },
+                "type_of_material": "News",
+                "uri": format!("nyt://article/mock-{year}-{month}-{i}"),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "copyright": "Mock",
+        "response": { "meta": { "hits": docs.len() }, "docs": docs }
+    })
+}
+
+/// The month's json, from scrapes/ if we have it
+fn month_body(date: NaiveDate) -> Vec<u8> {
+    match read_scrape(date) {
+        Ok(body) => body,
+        Err(_) => serde_json::to_vec(&made_up_month(date)).unwrap(),
+    }
+}
+
+async fn archive(
+    State(state): State<Arc<MockState>>,
+    Path((year, file)): Path<(i32, String)>,
+    Query(params): Query<KeyParams>,
+) -> Response {
+    let malformed = match gate(&state, params.api_key.as_deref()).await {
+        Ok(malformed) => malformed,
+        Err(resp) => return resp,
+    };
+    let Some(date) = file
+        .strip_suffix(".json")
+        .and_then(|month| month.parse::<u32>().ok())
+        .and_then(|month| NaiveDate::from_ymd_opt(year, month, 1))
+    else {
+        return (StatusCode::NOT_FOUND, "no such month").into_response();
+    };
+    println!("archive {year}/{}", date.month());
+    serve_json(month_body(date), malformed)
+}
+
+/// Pages of 10, like the real thing, over the archive docs published between the dates
+async fn article_search(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<SearchParams>,
+) -> Response {
+    let malformed = match gate(&state, params.api_key.as_deref()).await {
+        Ok(malformed) => malformed,
+        Err(resp) => return resp,
+    };
+    let (Ok(begin), Ok(end)) = (
+        NaiveDate::parse_from_str(&params.begin_date, "%Y%m%d"),
+        NaiveDate::parse_from_str(&params.end_date, "%Y%m%d"),
+    ) else {
+        return (StatusCode::BAD_REQUEST, "dates are YYYYMMDD").into_response();
+    };
+    let mut docs = vec![];
+    let mut month = begin.with_day(1).unwrap();
+    while month <= end {
+        let json =
+            serde_json::from_slice::<serde_json::Value>(&month_body(month)).unwrap_or_default();
+        if let Some(month_docs) = json["response"]["docs"].as_array() {
+            docs.extend(
+                month_docs
+                    .iter()
+                    .filter(|doc| {
+                        doc["pub_date"]
+                            .as_str()
+                            .and_then(|date| date.get(..10))
+                            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+                            .is_some_and(|date| begin <= date && date <= end)
+                    })
+                    .cloned(),
+            );
+        }
+        month = month.checked_add_months(chrono::Months::new(1)).unwrap();
+    }
+    let hits = docs.len();
+    let page = docs
+        .into_iter()
+        .skip(params.page * 10)
+        .take(10)
+        .collect::<Vec<_>>();
+    println!("search {begin}..={end} page {}", params.page);
+    let body = json!({
+        "status": "OK",
+        "copyright": "Mock",
+        "response": { "docs": page, "meta": { "hits": hits, "offset": params.page * 10, "time": 1 } }
+    });
+    serve_json(serde_json::to_vec(&body).unwrap(), malformed)
+}
+
+async fn top_stories(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<KeyParams>,
+) -> Response {
+    let malformed = match gate(&state, params.api_key.as_deref()).await {
+        Ok(malformed) => malformed,
+        Err(resp) => return resp,
+    };
+    match tokio::fs::read(CURRENT_FIXTURE).await {
+        Ok(body) => {
+            println!("top stories");
+            serve_json(body, malformed)
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{CURRENT_FIXTURE}: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Archive, article search and top stories, breaking things as `faults` says
+pub fn router(faults: Faults) -> Router {
+    let state = Arc::new(MockState {
+        faults,
+        requests: AtomicU64::new(0),
+    });
+    Router::new()
+        .route("/svc/archive/v1/:year/:file", get(archive))
+        .route("/svc/topstories/v2/home.json", get(top_stories))
+        .route("/svc/search/v2/articlesearch.json", get(article_search))
+        .with_state(state)
+}
+
+/// Serves `router(faults)` on `listener` until something goes wrong. Tests can bind port 0 and
+/// run this in the background.
+pub async fn serve(listener: TcpListener, faults: Faults) -> anyhow::Result<()> {
+    axum::serve(listener, router(faults)).await?;
+    Ok(())
+}
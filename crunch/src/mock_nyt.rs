@@ -0,0 +1,32 @@
+use cyclicism::mock::{serve, Faults};
+
+const ADDR: &str = "127.0.0.1:6480";
+
+const USAGE: &str = r#"A stand-in for api.nytimes.com, for running the scraper and updater offline.
+
+    cargo run --bin mock_nyt -- [--429 N] [--500 N] [--malformed N] [--slow MS]
+
+    --429 N        every Nth request gets a 429 with Retry-After: 1
+    --500 N        every Nth request gets a 503
+    --malformed N  every Nth request gets a truncated body
+    --slow MS      wait this long before every response
+
+Then point things at it with NYT_BASE_URL=http://127.0.0.1:6480 (any NYT_API_KEY works,
+except "bad", which gets a 401).
+
+Archive months come from scrapes/ when they're there, and are made up otherwise. Article
+search pages through the same docs. Top stories are example_current.json."#;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let faults = Faults::from_args(&args)?;
+    println!("Faults: {:?}", faults);
+    println!("Mock NYT api on http://{ADDR}");
+    let listener = tokio::net::TcpListener::bind(ADDR).await?;
+    serve(listener, faults).await
+}
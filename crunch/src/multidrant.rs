@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use qdrant_client::{
+    qdrant::{
+        vectors_config::Config, CountPointsBuilder, CreateCollectionBuilder, Distance, PointStruct,
+        QueryPointsBuilder, UpdateCollectionBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+        VectorsConfigBuilder,
+    },
+    Qdrant,
+};
+use uuid::Uuid;
+
+use crate::{
+    embed::Embedder,
+    junk::JunkFilter,
+    mydrant::{point_to_info, BedSource, Builds, CommonInfo, IndexConfig},
+    nyt::{uri_to_uuid, ScrapedArticle},
+};
+
+/// Every vector we could make for an article, along with the info needed to put it in qdrant
+pub struct MultiEmbedding {
+    pub uuid: Uuid,
+    pub beds: HashMap<BedSource, Vec<f32>>,
+    pub info: CommonInfo,
+}
+
+/// The text to embed for each `BedSource` an article has
+pub type SourceTexts = Vec<(BedSource, String)>;
+
+/// Pulls out the text for each of `sources` that the article actually has, skipping junk articles
+pub fn break_article_for_multidrant(
+    article: ScrapedArticle,
+    sources: &[BedSource],
+    junk: &JunkFilter,
+) -> Option<(Uuid, SourceTexts, CommonInfo)> {
+    if junk.check_scraped(&article).is_some() {
+        return None;
+    }
+    let texts = sources
+        .iter()
+        .filter_map(|source| source.text(&article).map(|text| (*source, text)))
+        .collect::<Vec<_>>();
+    if texts.is_empty() {
+        return None;
+    }
+    Some((uri_to_uuid(&article.uri), texts, article.into()))
+}
+
+/// A single collection where each point has a named vector per `BedSource`, so we can compare e.g
+/// "headline vs headline" and "headline vs snippet" without keeping parallel collections in sync.
+/// Built and published the same blue/green way as `Collection`.
+pub struct MultiCollection {
+    /// Which parts of the article get their own vector
+    sources: Vec<BedSource>,
+    bed_dim: u64,
+    /// Which model was used to do this embedding? (see `Embedder::model_id`)
+    model_id: String,
+    distance: Distance,
+    index: IndexConfig,
+    /// Which build to use, `None` for the live alias (see `Collection`)
+    version: Option<String>,
+    client: Qdrant,
+}
+impl MultiCollection {
+    pub fn new(
+        sources: Vec<BedSource>,
+        embedder: &dyn Embedder,
+        distance: Distance,
+        client: Qdrant,
+    ) -> Self {
+        Self {
+            sources,
+            bed_dim: embedder.dim(),
+            model_id: embedder.model_id(),
+            distance,
+            index: IndexConfig::DEFAULT,
+            version: None,
+            client,
+        }
+    }
+
+    /// Applies to every named vector
+    pub fn with_index_config(mut self, index: IndexConfig) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Points this collection at one specific build rather than the live alias
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn sources(&self) -> &[BedSource] {
+        &self.sources
+    }
+
+    /// The stable name readers use, see `Collection::alias_name`
+    pub fn alias_name(&self) -> String {
+        format!("Multi___{}___{:?}", self.model_id, self.distance)
+    }
+
+    fn builds(&self) -> Builds<'_> {
+        Builds {
+            client: &self.client,
+            alias: self.alias_name(),
+        }
+    }
+
+    fn collection_name(&self) -> String {
+        self.builds().name(self.version.as_deref())
+    }
+
+    /// All the builds that exist in qdrant, oldest first
+    pub async fn list_versions(&self) -> anyhow::Result<Vec<String>> {
+        self.builds().list_versions().await
+    }
+
+    /// The build the alias currently points at, if there is one
+    pub async fn live_version(&self) -> anyhow::Result<Option<String>> {
+        self.builds().live_version().await
+    }
+
+    /// Points the alias at this build. Errors if this collection has no version.
+    pub async fn promote(&self) -> anyhow::Result<()> {
+        let Some(version) = self.version.as_ref() else {
+            return Err(anyhow::anyhow!("Can only promote a specific version"));
+        };
+        self.verify_name(self.builds().versioned_name(version))
+            .await?;
+        self.builds().point_alias_at(version).await
+    }
+
+    /// Re-points the alias at the build before the live one. Returns the version now live.
+    pub async fn rollback(&self) -> anyhow::Result<String> {
+        let previous = self.builds().previous_version().await?;
+        self.verify_name(self.builds().versioned_name(&previous))
+            .await?;
+        self.builds().point_alias_at(&previous).await?;
+        Ok(previous)
+    }
+
+    pub async fn point_count(&self) -> anyhow::Result<u64> {
+        let res = self
+            .client
+            .count(CountPointsBuilder::new(self.collection_name()).exact(true))
+            .await?;
+        Ok(res.result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Like `Collection::verify`, for each named vector. Errors if any of `sources` is missing or
+    /// doesn't match the model and distance.
+    pub async fn verify(&self) -> anyhow::Result<()> {
+        self.verify_name(self.collection_name()).await
+    }
+
+    async fn verify_name(&self, name: String) -> anyhow::Result<()> {
+        let info = self
+            .client
+            .collection_info(&name)
+            .await?
+            .result
+            .ok_or(anyhow::anyhow!("No info for {}", name))?;
+        let Some(Config::ParamsMap(params_map)) = info
+            .config
+            .and_then(|c| c.params)
+            .and_then(|p| p.vectors_config)
+            .and_then(|v| v.config)
+        else {
+            return Err(anyhow::anyhow!("{} does not have named vectors", name));
+        };
+        for source in self.sources.iter() {
+            let Some(params) = params_map.map.get(&source.vector_name()) else {
+                return Err(anyhow::anyhow!("{} has no {:?} vectors", name, source));
+            };
+            if params.size != self.bed_dim || params.distance() != self.distance {
+                return Err(anyhow::anyhow!(
+                    "{}'s {:?} vectors are size {} with {:?} distance, but the model gives size {} and we want {:?}",
+                    name,
+                    source,
+                    params.size,
+                    params.distance(),
+                    self.bed_dim,
+                    self.distance
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn ensure_created(&self) -> anyhow::Result<()> {
+        let mut vectors_config = VectorsConfigBuilder::default();
+        for source in self.sources.iter() {
+            vectors_config.add_named_vector_params(
+                source.vector_name(),
+                VectorParamsBuilder::new(self.bed_dim, self.distance).on_disk(self.index.on_disk),
+            );
+        }
+        let (create, update) = self.index.configure(
+            CreateCollectionBuilder::new(self.collection_name()).vectors_config(vectors_config),
+            UpdateCollectionBuilder::new(self.collection_name()),
+        );
+        if self
+            .client
+            .collection_exists(self.collection_name())
+            .await?
+        {
+            self.verify().await?;
+        } else {
+            self.client.create_collection(create).await?;
+        }
+        self.client.update_collection(update).await?;
+        Ok(())
+    }
+
+    /// Points can be missing some of the vectors (e.g articles without keywords)
+    pub async fn upsert(&self, data: Vec<MultiEmbedding>) -> anyhow::Result<()> {
+        let points = data
+            .into_iter()
+            .map(|details| {
+                let beds = details
+                    .beds
+                    .into_iter()
+                    .map(|(source, bed)| (source.vector_name(), bed))
+                    .collect::<HashMap<_, _>>();
+                PointStruct::new(details.uuid.to_string(), beds, details.info)
+            })
+            .collect::<Vec<_>>();
+        self.client
+            .upsert_points(UpsertPointsBuilder::new(self.collection_name(), points).wait(true))
+            .await?;
+        Ok(())
+    }
+
+    /// Finds the `k` points whose `using` vector is closest to `bed`
+    pub async fn top_k(
+        &self,
+        bed: Vec<f32>,
+        using: BedSource,
+        k: u64,
+    ) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        if bed.len() as u64 != self.bed_dim {
+            return Err(anyhow::anyhow!(
+                "bed is not the right size, got {}, expected {}",
+                bed.len(),
+                self.bed_dim
+            ));
+        }
+        if !self.sources.contains(&using) {
+            return Err(anyhow::anyhow!(
+                "{} has no {:?} vectors",
+                self.collection_name(),
+                using
+            ));
+        }
+        let res = self
+            .client
+            .query(
+                QueryPointsBuilder::new(self.collection_name())
+                    .query(bed)
+                    .using(using.vector_name())
+                    .limit(k)
+                    .with_payload(true)
+                    .params(self.index.search_params(false, None)),
+            )
+            .await?;
+        Ok(res
+            .result
+            .into_iter()
+            .filter_map(|p| point_to_info(&p).map(|info| (info, p.score)))
+            .collect())
+    }
+
+    /// Scores points by a weighted sum of their similarity under each named vector.
+    /// Each vector is searched separately for its top `k * weights.len()`, so a point that is
+    /// great under one vector but missing from another's shortlist just gets no credit for that one.
+    pub async fn top_k_weighted(
+        &self,
+        bed: Vec<f32>,
+        weights: &[(BedSource, f32)],
+        k: u64,
+    ) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        let shortlist = k * weights.len().max(1) as u64;
+        let mut combined: HashMap<String, (CommonInfo, f32)> = HashMap::new();
+        for (source, weight) in weights {
+            for (info, score) in self.top_k(bed.clone(), *source, shortlist).await? {
+                combined.entry(info.uri.clone()).or_insert((info, 0.0)).1 += weight * score;
+            }
+        }
+        let mut results = combined.into_values().collect::<Vec<_>>();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.truncate(k as usize);
+        Ok(results)
+    }
+}
@@ -1,21 +1,62 @@
 use chrono::Datelike;
-use fastembed::EmbeddingModel;
 use qdrant_client::{
     qdrant::{
-        CreateCollectionBuilder, Distance, OptimizersConfigDiffBuilder, PointStruct, PointsIdsList,
-        QueryPointsBuilder, SetPayloadPointsBuilder, UpdateCollectionBuilder, UpsertPointsBuilder,
-        VectorParamsBuilder,
+        alias_operations::Action, vectors::VectorsOptions, vectors_config::Config, AliasOperations,
+        ChangeAliases, CompressionRatio, Condition, CountPointsBuilder, CreateAlias,
+        CreateCollectionBuilder, DeleteAlias, Distance, Filter, HnswConfigDiffBuilder,
+        OptimizersConfigDiffBuilder, PointId, PointStruct, PointsIdsList,
+        ProductQuantizationBuilder, QuantizationSearchParamsBuilder, QuantizationType,
+        QueryPointsBuilder, Range, ScalarQuantizationBuilder, ScoredPoint, ScrollPointsBuilder,
+        SearchParams, SearchParamsBuilder, SetPayloadPointsBuilder, SnapshotDownloadBuilder,
+        UpdateCollectionBuilder, UpsertPointsBuilder, Value, VectorParamsBuilder, Vectors,
     },
     Payload, Qdrant,
 };
+use std::{collections::HashMap, path::Path};
 use uuid::Uuid;
 
-use crate::nyt::{parse_pub_date, uri_to_uuid, ScrapedArticle};
+use crate::{
+    embed::Embedder,
+    junk::JunkFilter,
+    nyt::{clean_snippet, parse_pub_date, uri_to_uuid, ScrapedArticle},
+};
+
+/// What a collection of `source` vectors from `model_id` is called, before any versioning
+pub fn base_collection_name(source: BedSource, model_id: &str, distance: Distance) -> String {
+    format!("{:?}___{}___{:?}", source, model_id, distance)
+}
 
 /// Identifies what part of the article should do the embedding
-#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum BedSource {
     HeadlineMain,
+    Snippet,
+    Keywords,
+}
+impl BedSource {
+    /// The text this source pulls out of an article, or `None` if the article doesn't have any
+    pub fn text(&self, article: &ScrapedArticle) -> Option<String> {
+        let text = match self {
+            BedSource::HeadlineMain => article.headline.main.clone(),
+            BedSource::Snippet => clean_snippet(article.snippet.clone()),
+            BedSource::Keywords => article
+                .keywords
+                .iter()
+                .map(|k| k.value.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        if text.trim().is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    /// What the vector is called in collections that hold several per point
+    pub fn vector_name(&self) -> String {
+        format!("{:?}", self)
+    }
 }
 
 /// Information that should be attached to all point structs to allow for interesting filtering
@@ -30,33 +71,38 @@ pub struct CommonInfo {
     pub news_desk: String,
     pub type_of_material: String,
 }
-impl Into<Payload> for CommonInfo {
-    fn into(self) -> Payload {
-        let json_string = serde_json::to_string(&self).unwrap();
+impl From<CommonInfo> for Payload {
+    fn from(info: CommonInfo) -> Self {
+        let json_string = serde_json::to_string(&info).unwrap();
         serde_json::from_str(&json_string).unwrap()
     }
 }
+impl From<ScrapedArticle> for CommonInfo {
+    fn from(article: ScrapedArticle) -> Self {
+        let naive_date = parse_pub_date(&article.pub_date);
+        CommonInfo {
+            uri: article.uri,
+            year: naive_date.year() as u32,
+            month: naive_date.month0() + 1,
+            day: naive_date.day(),
+            print_section: article.print_section,
+            document_type: article.document_type,
+            news_desk: article.news_desk,
+            type_of_material: article.type_of_material,
+        }
+    }
+}
+/// Pulls out the text to embed, or `None` if the article has none or is junk
 pub fn break_article_for_mydrant(
     article: ScrapedArticle,
     source: BedSource,
+    junk: &JunkFilter,
 ) -> Option<(Uuid, String, CommonInfo)> {
-    let naive_date = parse_pub_date(&article.pub_date);
-    match source {
-        BedSource::HeadlineMain => Some((
-            uri_to_uuid(&article.uri),
-            article.headline.main,
-            CommonInfo {
-                uri: article.uri,
-                year: naive_date.year() as u32,
-                month: naive_date.month0() + 1,
-                day: naive_date.day(),
-                print_section: article.print_section,
-                document_type: article.document_type,
-                news_desk: article.news_desk,
-                type_of_material: article.type_of_material,
-            },
-        )),
+    if junk.check_scraped(&article).is_some() {
+        return None;
     }
+    let text = source.text(&article)?;
+    Some((uri_to_uuid(&article.uri), text, article.into()))
 }
 
 // An embedding along with the info needed to put it in qdrant
@@ -66,56 +112,454 @@ pub struct DetailedEmbedding {
     pub info: CommonInfo,
 }
 
+/// A potential match pulled from qdrant, along with its vector so it can be compared to other matches
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub info: CommonInfo,
+    /// The score qdrant gave this candidate
+    pub score: f32,
+    /// The score given by the cross-encoder, if this candidate was reranked
+    pub rerank_score: Option<f32>,
+    pub bed: Vec<f32>,
+}
+impl Candidate {
+    /// How good of a match this is: the cross-encoder's logit squashed to 0-1 if it was reranked,
+    /// the qdrant score otherwise. The two aren't on comparable scales.
+    pub fn relevance(&self) -> f32 {
+        match self.rerank_score {
+            Some(logit) => 1.0 / (1.0 + (-logit).exp()),
+            None => self.score,
+        }
+    }
+}
+
+fn payload_to_info(payload: &HashMap<String, Value>) -> Option<CommonInfo> {
+    let serde_string = serde_json::to_string(payload).ok()?;
+    serde_json::from_str::<CommonInfo>(&serde_string).ok()
+}
+
+fn vectors_to_bed(vectors: Option<Vectors>) -> Option<Vec<f32>> {
+    match vectors?.vectors_options? {
+        VectorsOptions::Vector(vector) => Some(vector.data),
+        VectorsOptions::Vectors(_) => None,
+    }
+}
+
+pub(crate) fn point_to_info(point: &ScoredPoint) -> Option<CommonInfo> {
+    payload_to_info(&point.payload)
+}
+
+fn point_to_bed(point: ScoredPoint) -> Option<Vec<f32>> {
+    vectors_to_bed(point.vectors)
+}
+
+/// Only points published between `start_year` and `end_year` (inclusive)
+pub fn year_filter(start_year: u32, end_year: u32) -> Filter {
+    Filter::must([Condition::range(
+        "year",
+        Range {
+            gte: Some(start_year as f64),
+            lte: Some(end_year as f64),
+            ..Default::default()
+        },
+    )])
+}
+
+/// How (and whether) vectors should be compressed in memory
+#[derive(Debug, Clone, Copy)]
+pub enum Quantization {
+    Off,
+    /// float32 -> int8, ~4x smaller. `quantile` clips outliers before quantizing (qdrant defaults to 1.0).
+    Scalar {
+        quantile: Option<f32>,
+        always_ram: bool,
+    },
+    /// Much smaller than scalar, but noticeably worse recall without rescoring
+    Product {
+        compression: CompressionRatio,
+        always_ram: bool,
+    },
+}
+
+/// Knobs for how qdrant stores and searches the collection. Storage settings only apply when the
+/// collection is created (except HNSW and quantization, which qdrant can rebuild in place).
+#[derive(Debug, Clone, Copy)]
+pub struct IndexConfig {
+    pub quantization: Quantization,
+    /// Keep the original vectors on disk (mmap) rather than in RAM
+    pub on_disk: bool,
+    /// Edges per node in the HNSW graph. `None` leaves qdrant's default (16).
+    pub hnsw_m: Option<u64>,
+    /// Neighbours considered while building the graph. `None` leaves qdrant's default (100).
+    pub hnsw_ef_construct: Option<u64>,
+    /// Neighbours considered at search time. `None` leaves qdrant's default.
+    pub hnsw_ef: Option<u64>,
+    /// When quantized, re-score the shortlist with the original vectors
+    pub rescore: bool,
+    /// When quantized, how many times `k` to shortlist before rescoring
+    pub oversampling: Option<f64>,
+}
+impl IndexConfig {
+    /// Everything left to qdrant, which is how collections were made before this existed
+    pub const DEFAULT: Self = Self {
+        quantization: Quantization::Off,
+        on_disk: false,
+        hnsw_m: None,
+        hnsw_ef_construct: None,
+        hnsw_ef: None,
+        rescore: true,
+        oversampling: None,
+    };
+
+    fn hnsw_config(&self) -> HnswConfigDiffBuilder {
+        let mut hnsw = HnswConfigDiffBuilder::default();
+        if let Some(m) = self.hnsw_m {
+            hnsw = hnsw.m(m);
+        }
+        if let Some(ef_construct) = self.hnsw_ef_construct {
+            hnsw = hnsw.ef_construct(ef_construct);
+        }
+        hnsw
+    }
+
+    /// Puts the HNSW and quantization settings on both the create request (for a new
+    /// collection) and the update request (so an existing one picks up changes). Whether vectors
+    /// live on disk is per vector, so that's left to the caller.
+    pub(crate) fn configure(
+        &self,
+        create: CreateCollectionBuilder,
+        update: UpdateCollectionBuilder,
+    ) -> (CreateCollectionBuilder, UpdateCollectionBuilder) {
+        let mut create = create.hnsw_config(self.hnsw_config());
+        let mut update = update
+            .optimizers_config(OptimizersConfigDiffBuilder::default())
+            .hnsw_config(self.hnsw_config());
+        match self.quantization {
+            Quantization::Off => {}
+            Quantization::Scalar {
+                quantile,
+                always_ram,
+            } => {
+                let mut scalar = ScalarQuantizationBuilder::default()
+                    .r#type(QuantizationType::Int8.into())
+                    .always_ram(always_ram);
+                if let Some(quantile) = quantile {
+                    scalar = scalar.quantile(quantile);
+                }
+                let scalar = scalar.build();
+                create = create.quantization_config(scalar);
+                update = update.quantization_config(scalar);
+            }
+            Quantization::Product {
+                compression,
+                always_ram,
+            } => {
+                let product = ProductQuantizationBuilder::new(compression.into())
+                    .always_ram(always_ram)
+                    .build();
+                create = create.quantization_config(product);
+                update = update.quantization_config(product);
+            }
+        }
+        (create, update)
+    }
+
+    pub(crate) fn search_params(&self, exact: bool, hnsw_ef: Option<u64>) -> SearchParams {
+        let mut params = SearchParamsBuilder::default().exact(exact);
+        if let Some(ef) = hnsw_ef.or(self.hnsw_ef) {
+            params = params.hnsw_ef(ef);
+        }
+        if !matches!(self.quantization, Quantization::Off) {
+            let mut quantization = QuantizationSearchParamsBuilder::default().rescore(self.rescore);
+            if let Some(oversampling) = self.oversampling {
+                quantization = quantization.oversampling(oversampling);
+            }
+            params = params.quantization(quantization);
+        }
+        params.build()
+    }
+}
+
+/// The blue/green scheme `Collection` and `MultiCollection` share: every build is a real
+/// collection named `{alias}___v{version}`, and readers go through `alias`, which gets switched
+/// from one build to the next.
+pub(crate) struct Builds<'a> {
+    pub(crate) client: &'a Qdrant,
+    pub(crate) alias: String,
+}
+impl Builds<'_> {
+    fn prefix(&self) -> String {
+        format!("{}___v", self.alias)
+    }
+
+    pub(crate) fn versioned_name(&self, version: &str) -> String {
+        format!("{}{}", self.prefix(), version)
+    }
+
+    /// The collection to read and write: a specific build, or the alias for whatever's live
+    pub(crate) fn name(&self, version: Option<&str>) -> String {
+        match version {
+            Some(version) => self.versioned_name(version),
+            None => self.alias.clone(),
+        }
+    }
+
+    /// Oldest first
+    pub(crate) async fn list_versions(&self) -> anyhow::Result<Vec<String>> {
+        let prefix = self.prefix();
+        let mut versions = self
+            .client
+            .list_collections()
+            .await?
+            .collections
+            .into_iter()
+            .filter_map(|c| c.name.strip_prefix(&prefix).map(|v| v.to_string()))
+            .collect::<Vec<_>>();
+        versions.sort();
+        Ok(versions)
+    }
+
+    pub(crate) async fn live_version(&self) -> anyhow::Result<Option<String>> {
+        let prefix = self.prefix();
+        Ok(self
+            .client
+            .list_aliases()
+            .await?
+            .aliases
+            .into_iter()
+            .find(|a| a.alias_name == self.alias)
+            .and_then(|a| {
+                a.collection_name
+                    .strip_prefix(&prefix)
+                    .map(|v| v.to_string())
+            }))
+    }
+
+    /// The build before the live one, for rolling back to
+    pub(crate) async fn previous_version(&self) -> anyhow::Result<String> {
+        let Some(live) = self.live_version().await? else {
+            return Err(anyhow::anyhow!("{} isn't an alias yet", self.alias));
+        };
+        self.list_versions()
+            .await?
+            .into_iter()
+            .rev()
+            .find(|v| *v < live)
+            .ok_or(anyhow::anyhow!("Nothing older than {live} to roll back to"))
+    }
+
+    /// Switches the alias to `version`. Check the build first, readers start hitting it as soon
+    /// as this returns.
+    pub(crate) async fn point_alias_at(&self, version: &str) -> anyhow::Result<()> {
+        let live = self.live_version().await?;
+        if live.is_none() && self.client.collection_exists(&self.alias).await? {
+            return Err(anyhow::anyhow!(
+                "{} is a real collection, not an alias. Delete it (or snapshot it) before promoting.",
+                self.alias
+            ));
+        }
+        let mut actions = vec![];
+        if live.is_some() {
+            actions.push(Action::DeleteAlias(DeleteAlias {
+                alias_name: self.alias.clone(),
+            }));
+        }
+        actions.push(Action::CreateAlias(CreateAlias {
+            collection_name: self.versioned_name(version),
+            alias_name: self.alias.clone(),
+        }));
+        change_aliases(self.client, actions).await
+    }
+}
+
+/// Sends `actions` as one request, which qdrant applies atomically, so readers never see the
+/// alias missing between a delete and a create. `Qdrant` only sends one action per request, so
+/// this goes through the older client on the same connection settings.
+#[allow(deprecated)]
+async fn change_aliases(client: &Qdrant, actions: Vec<Action>) -> anyhow::Result<()> {
+    let config = &client.config;
+    let mut old_config = qdrant_client::client::QdrantClientConfig::from_url(&config.uri);
+    old_config.timeout = config.timeout;
+    old_config.connect_timeout = config.connect_timeout;
+    old_config.keep_alive_while_idle = config.keep_alive_while_idle;
+    old_config.api_key = config.api_key.clone();
+    qdrant_client::client::QdrantClient::new(Some(old_config))?
+        .update_aliases(ChangeAliases {
+            actions: actions
+                .into_iter()
+                .map(|action| AliasOperations {
+                    action: Some(action),
+                })
+                .collect(),
+            timeout: None,
+        })
+        .await?;
+    Ok(())
+}
+
 pub struct Collection {
     /// What part of the article are we embedding? (e.g headline, snippet...)
     source: BedSource,
-    bed_dim: u64, // TODO: Figure out how to infer this from fastembed (why is it not obvious?)
-    /// Which model was used to do this embedding?
-    model: EmbeddingModel,
+    /// How long are the vectors? Comes from the embedder, never hand-supplied.
+    bed_dim: u64,
+    /// Which model was used to do this embedding? (see `Embedder::model_id`)
+    model_id: String,
     /// What kind of distance metric to use on vectors?
     distance: Distance,
+    index: IndexConfig,
+    /// Which physical build of the collection to use. If `None`, we go through the alias,
+    /// which is what anything serving traffic should do.
+    version: Option<String>,
     client: Qdrant,
 }
 impl Collection {
     pub fn new(
         source: BedSource,
-        bed_dim: u64,
-        model: EmbeddingModel,
+        embedder: &dyn Embedder,
         distance: Distance,
         client: Qdrant,
     ) -> Self {
         Self {
             source,
-            bed_dim,
-            model,
+            bed_dim: embedder.dim(),
+            model_id: embedder.model_id(),
             distance,
+            index: IndexConfig::DEFAULT,
+            version: None,
             client,
         }
     }
 
+    pub fn with_index_config(mut self, index: IndexConfig) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Points this collection at one specific build rather than the live alias
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    /// A fresh version name. These sort chronologically, which `rollback` relies on.
+    pub fn new_version() -> String {
+        chrono::Utc::now().format("%Y%m%d%H%M%S").to_string()
+    }
+
+    /// The length of every vector in the collection
+    pub fn bed_dim(&self) -> u64 {
+        self.bed_dim
+    }
+
+    /// The stable name readers use. Once anything has been promoted this is a qdrant alias.
+    pub fn alias_name(&self) -> String {
+        base_collection_name(self.source, &self.model_id, self.distance)
+    }
+
+    fn builds(&self) -> Builds<'_> {
+        Builds {
+            client: &self.client,
+            alias: self.alias_name(),
+        }
+    }
+
+    fn versioned_name(&self, version: &str) -> String {
+        self.builds().versioned_name(version)
+    }
+
     fn collection_name(&self) -> String {
-        format!("{:?}___{:?}___{:?}", self.source, self.model, self.distance)
+        self.builds().name(self.version.as_deref())
+    }
+
+    /// All the builds of this collection that exist in qdrant, oldest first
+    pub async fn list_versions(&self) -> anyhow::Result<Vec<String>> {
+        self.builds().list_versions().await
+    }
+
+    /// The build the alias currently points at, if there is one
+    pub async fn live_version(&self) -> anyhow::Result<Option<String>> {
+        self.builds().live_version().await
+    }
+
+    /// Points the alias at this build. Errors if this collection has no version.
+    pub async fn promote(&self) -> anyhow::Result<()> {
+        let Some(version) = self.version.as_ref() else {
+            return Err(anyhow::anyhow!("Can only promote a specific version"));
+        };
+        self.verify_name(self.versioned_name(version)).await?;
+        self.builds().point_alias_at(version).await
+    }
+
+    /// Re-points the alias at the build before the live one. Returns the version now live.
+    pub async fn rollback(&self) -> anyhow::Result<String> {
+        let previous = self.builds().previous_version().await?;
+        self.verify_name(self.versioned_name(&previous)).await?;
+        self.builds().point_alias_at(&previous).await?;
+        Ok(previous)
+    }
+
+    pub async fn point_count(&self) -> anyhow::Result<u64> {
+        let res = self
+            .client
+            .count(CountPointsBuilder::new(self.collection_name()).exact(true))
+            .await?;
+        Ok(res.result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Errors if the collection in qdrant doesn't have the vector size and distance we expect.
+    /// A mis-sized collection would otherwise only show up as confusing failures on upsert/query.
+    pub async fn verify(&self) -> anyhow::Result<()> {
+        self.verify_name(self.collection_name()).await
+    }
+
+    async fn verify_name(&self, name: String) -> anyhow::Result<()> {
+        let info = self
+            .client
+            .collection_info(&name)
+            .await?
+            .result
+            .ok_or(anyhow::anyhow!("No info for {}", name))?;
+        let Some(Config::Params(params)) = info
+            .config
+            .and_then(|c| c.params)
+            .and_then(|p| p.vectors_config)
+            .and_then(|v| v.config)
+        else {
+            return Err(anyhow::anyhow!(
+                "{} does not have a single unnamed vector",
+                name
+            ));
+        };
+        if params.size != self.bed_dim || params.distance() != self.distance {
+            return Err(anyhow::anyhow!(
+                "{} has vectors of size {} with {:?} distance, but the model gives size {} and we want {:?}",
+                name,
+                params.size,
+                params.distance(),
+                self.bed_dim,
+                self.distance
+            ));
+        }
+        Ok(())
     }
 
     pub async fn ensure_created(&self) -> anyhow::Result<()> {
-        if !self
+        let (create, update) = self.index.configure(
+            CreateCollectionBuilder::new(self.collection_name()).vectors_config(
+                VectorParamsBuilder::new(self.bed_dim, self.distance).on_disk(self.index.on_disk),
+            ),
+            UpdateCollectionBuilder::new(self.collection_name()),
+        );
+        if self
             .client
             .collection_exists(self.collection_name())
             .await?
         {
-            self.client
-                .create_collection(
-                    CreateCollectionBuilder::new(self.collection_name())
-                        .vectors_config(VectorParamsBuilder::new(self.bed_dim, self.distance)),
-                )
-                .await?;
+            self.verify().await?;
+        } else {
+            self.client.create_collection(create).await?;
         }
-        self.client
-            .update_collection(
-                UpdateCollectionBuilder::new(self.collection_name())
-                    .optimizers_config(OptimizersConfigDiffBuilder::default()),
-            )
-            .await?;
+        self.client.update_collection(update).await?;
         Ok(())
     }
 
@@ -154,7 +598,7 @@ impl Collection {
         Ok(())
     }
 
-    pub async fn top_k(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+    fn check_bed(&self, bed: &[f32]) -> anyhow::Result<()> {
         if bed.len() as u64 != self.bed_dim {
             return Err(anyhow::anyhow!(
                 "bed is not the right size, got {}, expected {}",
@@ -162,27 +606,255 @@ impl Collection {
                 self.bed_dim
             ));
         }
-        let res = self
-            .client
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        bed: Vec<f32>,
+        k: u64,
+        with_vectors: bool,
+        params: SearchParams,
+        filter: Option<Filter>,
+    ) -> anyhow::Result<Vec<ScoredPoint>> {
+        self.check_bed(&bed)?;
+        let mut query = QueryPointsBuilder::new(self.collection_name())
+            .query(bed)
+            .limit(k)
+            .with_payload(true)
+            .with_vectors(with_vectors)
+            .params(params);
+        if let Some(filter) = filter {
+            query = query.filter(filter);
+        }
+        let res = self.client.query(query).await?;
+        Ok(res.result)
+    }
+
+    pub async fn top_k(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        self.top_k_with_ef(bed, k, None).await
+    }
+
+    /// Like `top_k`, but overriding the search-time `hnsw_ef` from the index config
+    pub async fn top_k_with_ef(
+        &self,
+        bed: Vec<f32>,
+        k: u64,
+        hnsw_ef: Option<u64>,
+    ) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        let points = self
             .query(
-                QueryPointsBuilder::new(self.collection_name())
-                    .query(bed)
-                    .limit(k)
-                    .with_payload(true),
+                bed,
+                k,
+                false,
+                self.index.search_params(false, hnsw_ef),
+                None,
             )
             .await?;
-        Ok(res
-            .result
+        Ok(points
             .into_iter()
-            .map(|p| {
-                let serde_string = serde_json::to_string(&p.payload).unwrap();
-                let info = serde_json::from_str::<CommonInfo>(&serde_string);
-                (info, p.score)
-            })
-            .filter_map(|(info, score)| match info {
-                Ok(thing) => Some((thing, score)),
-                _ => None,
+            .filter_map(|p| point_to_info(&p).map(|info| (info, p.score)))
+            .collect())
+    }
+
+    /// The true nearest neighbours, found by qdrant scanning every (original, unquantized) vector.
+    /// Slow, so only for measuring how much the index is costing us.
+    pub async fn top_k_exact(
+        &self,
+        bed: Vec<f32>,
+        k: u64,
+    ) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        let params = SearchParamsBuilder::default()
+            .exact(true)
+            .quantization(QuantizationSearchParamsBuilder::default().ignore(true))
+            .build();
+        let points = self.query(bed, k, false, params, None).await?;
+        Ok(points
+            .into_iter()
+            .filter_map(|p| point_to_info(&p).map(|info| (info, p.score)))
+            .collect())
+    }
+
+    /// Like `top_k`, but also returns the stored vectors so results can be compared to each other
+    pub async fn top_k_candidates(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<Candidate>> {
+        let points = self
+            .query(bed, k, true, self.index.search_params(false, None), None)
+            .await?;
+        Ok(points
+            .into_iter()
+            .filter_map(|p| {
+                let info = point_to_info(&p)?;
+                let score = p.score;
+                let bed = point_to_bed(p)?;
+                Some(Candidate {
+                    info,
+                    score,
+                    rerank_score: None,
+                    bed,
+                })
             })
             .collect())
     }
+
+    /// Like `top_k`, but only considering points published between `start_year` and `end_year`
+    pub async fn top_k_in_years(
+        &self,
+        bed: Vec<f32>,
+        k: u64,
+        start_year: u32,
+        end_year: u32,
+    ) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        let points = self
+            .query(
+                bed,
+                k,
+                false,
+                self.index.search_params(false, None),
+                Some(year_filter(start_year, end_year)),
+            )
+            .await?;
+        Ok(points
+            .into_iter()
+            .filter_map(|p| point_to_info(&p).map(|info| (info, p.score)))
+            .collect())
+    }
+
+    /// One page of points (with vectors), plus where the next page starts if there is one
+    pub async fn export_page(
+        &self,
+        filter: Option<Filter>,
+        offset: Option<PointId>,
+        limit: u32,
+    ) -> anyhow::Result<(Vec<DetailedEmbedding>, Option<PointId>)> {
+        let mut scroll = ScrollPointsBuilder::new(self.collection_name())
+            .limit(limit)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(filter) = filter {
+            scroll = scroll.filter(filter);
+        }
+        if let Some(offset) = offset {
+            scroll = scroll.offset(offset);
+        }
+        let res = self.client.scroll(scroll).await?;
+        let mut exported = vec![];
+        for point in res.result {
+            let Some(info) = payload_to_info(&point.payload) else {
+                continue;
+            };
+            // Point ids are always derived from the uri, see `break_article_for_mydrant`
+            let uuid = uri_to_uuid(&info.uri);
+            let Some(bed) = vectors_to_bed(point.vectors) else {
+                continue;
+            };
+            exported.push(DetailedEmbedding { uuid, bed, info });
+        }
+        Ok((exported, res.next_page_offset))
+    }
+
+    /// Pulls every vector (and its info) published between `start_year` and `end_year` out of qdrant
+    pub async fn export_years(
+        &self,
+        start_year: u32,
+        end_year: u32,
+    ) -> anyhow::Result<Vec<DetailedEmbedding>> {
+        const PAGE_SIZE: u32 = 1024;
+        let mut exported = vec![];
+        let mut offset = None;
+        loop {
+            let (page, next) = self
+                .export_page(Some(year_filter(start_year, end_year)), offset, PAGE_SIZE)
+                .await?;
+            exported.extend(page);
+            offset = next;
+            if offset.is_none() {
+                break;
+            }
+        }
+        Ok(exported)
+    }
+
+    /// The real collection behind this one, following the alias if we don't have a version.
+    /// Snapshots belong to collections, not aliases.
+    async fn resolved_name(&self) -> anyhow::Result<String> {
+        if self.version.is_some() {
+            return Ok(self.collection_name());
+        }
+        Ok(self.builds().name(self.live_version().await?.as_deref()))
+    }
+
+    /// Has qdrant write a snapshot of the collection, returning the snapshot's name
+    pub async fn create_snapshot(&self) -> anyhow::Result<String> {
+        let name = self.resolved_name().await?;
+        self.client
+            .create_snapshot(name.clone())
+            .await?
+            .snapshot_description
+            .map(|description| description.name)
+            .ok_or(anyhow::anyhow!(
+                "qdrant didn't describe the snapshot of {}",
+                name
+            ))
+    }
+
+    /// Names of the snapshots qdrant has of this collection
+    pub async fn list_snapshots(&self) -> anyhow::Result<Vec<String>> {
+        let name = self.resolved_name().await?;
+        Ok(self
+            .client
+            .list_snapshots(name)
+            .await?
+            .snapshot_descriptions
+            .into_iter()
+            .map(|description| description.name)
+            .collect())
+    }
+
+    /// Downloads a snapshot to `out_path`. Snapshot files are served over the REST API
+    /// (`rest_url`, usually port 6333) rather than gRPC.
+    pub async fn download_snapshot(
+        &self,
+        snapshot: &str,
+        out_path: &Path,
+        rest_url: &str,
+    ) -> anyhow::Result<()> {
+        let name = self.resolved_name().await?;
+        self.client
+            .download_snapshot(
+                SnapshotDownloadBuilder::new(out_path, name)
+                    .snapshot_name(snapshot)
+                    .rest_api_uri(rest_url),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Uploads a snapshot file into this version on the qdrant at `rest_url`, replacing whatever
+    /// was there. Restore into a version and `promote` it rather than restoring over the alias.
+    pub async fn restore_snapshot(&self, path: &Path, rest_url: &str) -> anyhow::Result<()> {
+        if self.version.is_none() {
+            return Err(anyhow::anyhow!("Can only restore into a specific version"));
+        }
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+        let file_name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or("snapshot".to_string());
+        let form = reqwest::multipart::Form::new().part(
+            "snapshot",
+            reqwest::multipart::Part::stream_with_length(file, len).file_name(file_name),
+        );
+        reqwest::Client::new()
+            .post(format!(
+                "{}/collections/{}/snapshots/upload?priority=snapshot",
+                rest_url.trim_end_matches('/'),
+                self.collection_name()
+            ))
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?;
+        self.verify().await
+    }
 }
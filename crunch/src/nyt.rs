@@ -1,8 +1,26 @@
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
+
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDate};
 use regex::Regex;
+use serde::de::{DeserializeSeed, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 use uuid::Uuid;
 
-use crate::get_json_path;
+use crate::{
+    compress::{open_scrape, read_scrape},
+    lenient::{decode_article, DocOutcome, DocStats, Quarantine},
+    source::{NewsArticle, NewsImage, NewsSource},
+};
+
+/// `NewsSource::name` for the NYT, and what articles from before there were other sources are
+/// assumed to be from
+pub const NYT_SOURCE_NAME: &str = "nyt";
+
+/// How many parsed articles `ScrapedDocs` keeps ready before the parser waits
+const DOCS_BUFFER: usize = 256;
 
 #[derive(Debug, serde::Deserialize)]
 pub struct ScrapedMeta {
@@ -101,13 +119,167 @@ pub struct ScrapedJson {
 }
 impl ScrapedJson {
     pub fn from_date(date: NaiveDate) -> anyhow::Result<Self> {
-        let alleged_path = get_json_path(date);
-        let contents = std::fs::read_to_string(alleged_path)?;
-        let scraped_json: ScrapedJson = serde_json::from_str(&contents)?;
+        let contents = read_scrape(date)?;
+        let scraped_json: ScrapedJson = serde_json::from_slice(&contents)?;
         Ok(scraped_json)
     }
 }
 
+/// The articles in a month's scrape, one at a time. The file is parsed on a background thread
+/// as it's consumed, so neither the json nor the full `docs` array is ever held in memory.
+///
+/// Each doc is decoded on its own (see `lenient`), so one bad doc doesn't cost the whole month.
+pub struct ScrapedDocs {
+    rx: Receiver<anyhow::Result<ScrapedArticle>>,
+    stats: Arc<Mutex<DocStats>>,
+}
+impl ScrapedDocs {
+    pub fn open(date: NaiveDate) -> anyhow::Result<Self> {
+        let reader = open_scrape(date)?;
+        let (tx, rx) = channel(DOCS_BUFFER);
+        let stats = Arc::new(Mutex::new(DocStats::default()));
+        let sink = DocsSink {
+            tx,
+            stats: stats.clone(),
+            quarantine: RefCell::new(Quarantine::for_month(date)),
+        };
+        std::thread::spawn(move || {
+            let mut de = serde_json::Deserializer::from_reader(reader);
+            let res = DocsVisitor {
+                sink: &sink,
+                level: DocsLevel::Root,
+            }
+            .deserialize(&mut de)
+            .and_then(|_| de.end())
+            .map_err(anyhow::Error::from)
+            // Only a complete read says what the month's quarantine should be
+            .and_then(|_| sink.quarantine.into_inner().finish());
+            // If the receiver's gone this fails too, which is fine
+            if let Err(e) = res {
+                sink.tx.blocking_send(Err(e)).ok();
+            }
+        });
+        Ok(Self { rx, stats })
+    }
+
+    /// How many docs have been parsed, coerced and skipped so far. Final once `next` is `None`.
+    pub fn stats(&self) -> DocStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// `None` once the month is done. An `Err` is the last thing you'll get.
+    pub async fn next(&mut self) -> Option<anyhow::Result<ScrapedArticle>> {
+        self.rx.recv().await
+    }
+
+    /// Up to `n` articles, empty once the month is done
+    pub async fn next_chunk(&mut self, n: usize) -> anyhow::Result<Vec<ScrapedArticle>> {
+        let mut chunk = Vec::with_capacity(n);
+        while chunk.len() < n {
+            match self.next().await {
+                Some(article) => chunk.push(article?),
+                None => break,
+            }
+        }
+        Ok(chunk)
+    }
+}
+
+/// Where `DocsVisitor` is in `{ "response": { "docs": [...] } }`
+#[derive(Clone, Copy)]
+enum DocsLevel {
+    Root,
+    Response,
+    Docs,
+}
+
+/// Where decoded docs go, on the parsing thread
+struct DocsSink {
+    tx: Sender<anyhow::Result<ScrapedArticle>>,
+    stats: Arc<Mutex<DocStats>>,
+    quarantine: RefCell<Quarantine>,
+}
+impl DocsSink {
+    /// Decodes, counts and forwards one doc. Errs if we should stop parsing.
+    fn handle(&self, doc: serde_json::Value) -> Result<(), String> {
+        let article = match decode_article(&doc) {
+            DocOutcome::Parsed(article) => {
+                self.stats.lock().unwrap().parsed += 1;
+                article
+            }
+            DocOutcome::Coerced(article) => {
+                self.stats.lock().unwrap().coerced += 1;
+                article
+            }
+            DocOutcome::Skipped(e) => {
+                self.stats.lock().unwrap().skipped += 1;
+                return self
+                    .quarantine
+                    .borrow_mut()
+                    .add(&doc, &e)
+                    .map_err(|e| format!("couldn't quarantine a doc: {e}"));
+            }
+        };
+        self.tx
+            .blocking_send(Ok(article))
+            .map_err(|_| "nobody's reading the docs anymore".to_string())
+    }
+}
+
+/// Walks down to the docs array, skipping everything else and handing each doc to the sink as
+/// soon as it's parsed
+struct DocsVisitor<'a> {
+    sink: &'a DocsSink,
+    level: DocsLevel,
+}
+impl<'de> DeserializeSeed<'de> for DocsVisitor<'_> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.level {
+            DocsLevel::Root | DocsLevel::Response => deserializer.deserialize_map(self),
+            DocsLevel::Docs => deserializer.deserialize_seq(self),
+        }
+    }
+}
+impl<'de> Visitor<'de> for DocsVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "an archive response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let (wanted, next) = match self.level {
+            DocsLevel::Root => ("response", DocsLevel::Response),
+            _ => ("docs", DocsLevel::Docs),
+        };
+        let mut found = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key != wanted {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            found = true;
+            map.next_value_seed(DocsVisitor {
+                sink: self.sink,
+                level: next,
+            })?;
+        }
+        if !found {
+            return Err(A::Error::missing_field(wanted));
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(doc) = seq.next_element::<serde_json::Value>()? {
+            self.sink.handle(doc).map_err(A::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct ContemporaryMultimedia {
     pub url: String,
@@ -148,7 +320,45 @@ impl ContemporaryArticle {
     pub fn get_date_parts(&self) -> anyhow::Result<(u32, u32, u32)> {
         let dt: DateTime<FixedOffset> =
             DateTime::parse_from_str(&self.published_date, "%Y-%m-%dT%H:%M:%S%:z").unwrap();
-        Ok((dt.year_ce().1 as u32, dt.month0() + 1, dt.day()))
+        Ok((dt.year_ce().1, dt.month0() + 1, dt.day()))
+    }
+
+    /// `None` if the published date doesn't parse
+    pub fn to_news(&self) -> Option<NewsArticle> {
+        Some(NewsArticle {
+            source: NYT_SOURCE_NAME.to_string(),
+            uri: self.uri.clone(),
+            url: self.url.clone(),
+            headline: self.title.clone(),
+            summary: self.abstract_.clone(),
+            published_at: DateTime::parse_from_rfc3339(&self.published_date).ok()?,
+            section: self.section.clone(),
+            subsection: self.subsection.clone(),
+            kind: self.item_type.clone(),
+            kicker: self.kicker.clone(),
+            type_of_material: self.material_type_facet.clone(),
+            tags: [
+                &self.des_facet,
+                &self.org_facet,
+                &self.per_facet,
+                &self.geo_facet,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+            image: self
+                .multimedia
+                .as_ref()
+                .and_then(|m| m.first())
+                .map(|m| NewsImage {
+                    url: m.url.clone(),
+                    caption: (!m.caption.is_empty()).then(|| m.caption.clone()),
+                    format: Some(m.format.clone()),
+                    type_: m.type_.clone(),
+                    subtype: Some(m.subtype.clone()),
+                }),
+        })
     }
 }
 
@@ -162,18 +372,74 @@ pub struct ContemporaryJson {
     pub results: Vec<ContemporaryArticle>,
 }
 
+/// Top stories for what's current, and our local scrapes of the Archive API for the past
+pub struct NytSource {
+    base_url: String,
+    api_key: String,
+}
+impl NytSource {
+    /// Uses `NYT_BASE_URL` and `NYT_API_KEY`
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            base_url: nyt_base_url(),
+            api_key: nyt_api_key()?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl NewsSource for NytSource {
+    fn name(&self) -> &str {
+        NYT_SOURCE_NAME
+    }
+
+    async fn latest(&self) -> anyhow::Result<Vec<NewsArticle>> {
+        Ok(get_current_homepage(&self.base_url, &self.api_key)
+            .await?
+            .iter()
+            .filter_map(|article| article.to_news())
+            .collect())
+    }
+}
+
+/// Where the NYT apis live if `NYT_BASE_URL` isn't set
+pub const DEFAULT_NYT_BASE_URL: &str = "https://api.nytimes.com";
+
+/// `NYT_BASE_URL` (or `DEFAULT_NYT_BASE_URL`), without a trailing slash. Point it at the
+/// `mock_nyt` bin to run things offline.
+pub fn nyt_base_url() -> String {
+    std::env::var("NYT_BASE_URL")
+        .unwrap_or(DEFAULT_NYT_BASE_URL.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
 /// Fetches the current articles on the homepage as `ContemporaryArticle`s.
-pub async fn get_current_homepage(api_key: &str) -> anyhow::Result<Vec<ContemporaryArticle>> {
-    let resp = reqwest::get(format!(
-        "https://api.nytimes.com/svc/topstories/v2/home.json?api-key={}",
-        api_key
-    ))
-    .await?;
+pub async fn get_current_homepage(
+    base_url: &str,
+    api_key: &str,
+) -> anyhow::Result<Vec<ContemporaryArticle>> {
+    let resp = reqwest::Client::new()
+        .get(format!("{base_url}/svc/topstories/v2/home.json"))
+        .query(&[("api-key", api_key)])
+        .send()
+        .await?
+        .error_for_status()?;
     let text = resp.text().await?;
     let obj = serde_json::from_str::<ContemporaryJson>(&text)?;
     Ok(obj.results)
 }
 
+/// Reads `NYT_API_KEY`, with an error that says what to do about it
+pub fn nyt_api_key() -> anyhow::Result<String> {
+    match std::env::var("NYT_API_KEY") {
+        Ok(key) if !key.trim().is_empty() => Ok(key),
+        _ => Err(anyhow::anyhow!(
+            "NYT_API_KEY isn't set. Make an app at https://developer.nytimes.com with the Archive and Top Stories APIs enabled, then export its key."
+        )),
+    }
+}
+
 pub fn uri_to_uuid(uri: &str) -> Uuid {
     Uuid::new_v3(&Uuid::NAMESPACE_URL, uri.as_bytes())
 }
@@ -197,6 +463,91 @@ pub struct FrontendImage {
     pub caption: Option<String>,
 }
 
+/// What the contemporary tables keep of an article, which is all we have to go on once it's off
+/// the homepage
+#[derive(Debug, Clone)]
+pub struct StoredContemporary {
+    /// `NewsSource::name`
+    pub source: String,
+    pub uri: String,
+    pub url: String,
+    pub date: NaiveDate,
+    pub title: String,
+    pub abstract_: String,
+    pub section: String,
+    pub subsection: String,
+    pub item_type: String,
+    pub kicker: String,
+    pub image: Option<StoredContemporaryImage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredContemporaryImage {
+    pub url: String,
+    pub format: Option<String>,
+    pub type_: String,
+    pub subtype: Option<String>,
+    pub caption: Option<String>,
+}
+
+impl StoredContemporary {
+    /// Dresses it up as an archive article, for when the Archive API never gives us the real one
+    pub fn to_scraped(&self) -> ScrapedArticle {
+        let non_empty = |s: &str| (!s.trim().is_empty()).then(|| s.to_string());
+        ScrapedArticle {
+            web_url: self.url.clone(),
+            snippet: self.abstract_.clone(),
+            print_page: None,
+            print_section: None,
+            source: match self.source.as_str() {
+                NYT_SOURCE_NAME => "The New York Times".to_string(),
+                other => other.to_string(),
+            },
+            multimedia: self
+                .image
+                .iter()
+                .map(|image| ScrapedMultimedia {
+                    rank: 0,
+                    subtype: image.subtype.clone().unwrap_or_default(),
+                    caption: image.caption.clone(),
+                    credit: None,
+                    type_: image.type_.clone(),
+                    url: image.url.clone(),
+                    height: 0,
+                    width: 0,
+                    legacy: ScrapedMultimediaLegacy {
+                        xlarge: None,
+                        xlargewidth: None,
+                        xlargeheight: None,
+                    },
+                    crop_name: image.format.clone().unwrap_or_default(),
+                })
+                .collect(),
+            headline: ScrapedHeadline {
+                main: self.title.clone(),
+                kicker: non_empty(&self.kicker),
+                content_kicker: None,
+                print_headline: self.title.clone(),
+                name: None,
+                seo: None,
+                sub: None,
+            },
+            keywords: vec![],
+            pub_date: format!("{}T00:00:00+00:00", self.date.format("%Y-%m-%d")),
+            document_type: "article".to_string(),
+            news_desk: self.section.clone(),
+            section_name: self.section.clone(),
+            byline: ScrapedByline {
+                original: String::new(),
+                person: vec![],
+                organization: None,
+            },
+            type_of_material: self.item_type.clone(),
+            uri: self.uri.clone(),
+        }
+    }
+}
+
 /// The "important" information from an article and it's associated stuff that we will eventually pass to frontend
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FrontendArticle {
@@ -0,0 +1,166 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    mydrant::{Collection, CommonInfo, DetailedEmbedding},
+    nyt::uri_to_uuid,
+};
+
+/// Plain export of a collection that doesn't depend on qdrant's snapshot format, so it survives
+/// qdrant upgrades and can be read from anything.
+///
+/// Layout (all integers little-endian):
+/// - `MAGIC`
+/// - u32 header length, then the `PortableHeader` as JSON
+/// - per point: u32 info length, the `CommonInfo` as JSON, then `dim` f32s
+const MAGIC: &[u8; 8] = b"CYCLVEC1";
+const PAGE_SIZE: u32 = 1024;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PortableHeader {
+    /// `Collection::alias_name`, which pins down the source, model and distance
+    pub collection: String,
+    pub dim: u64,
+}
+
+fn write_chunk(out: &mut impl Write, bytes: &[u8]) -> anyhow::Result<()> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)?;
+    Ok(())
+}
+
+/// `None` on a clean end of file, where there's nothing left at all. Running out anywhere inside
+/// a chunk (even its length) is an `UnexpectedEof` error, since the file was cut short.
+fn read_chunk(input: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match input.read(&mut len[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("cut off {filled} bytes into a chunk length"),
+                )
+                .into())
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+pub(crate) fn write_header(out: &mut impl Write, header: &PortableHeader) -> anyhow::Result<()> {
+    out.write_all(MAGIC)?;
+    write_chunk(out, &serde_json::to_vec(header)?)
+}
+
+pub(crate) fn write_point(
+    out: &mut impl Write,
+    info: &CommonInfo,
+    bed: &[f32],
+) -> anyhow::Result<()> {
+    write_chunk(out, &serde_json::to_vec(info)?)?;
+    for x in bed {
+        out.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_header(input: &mut impl Read, path: &Path) -> anyhow::Result<PortableHeader> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow::anyhow!("{:?} isn't a portable export", path));
+    }
+    let header = read_chunk(input)?.ok_or(anyhow::anyhow!("{:?} has no header", path))?;
+    Ok(serde_json::from_slice::<PortableHeader>(&header)?)
+}
+
+/// `None` once we run out of points
+pub(crate) fn read_point(
+    input: &mut impl Read,
+    dim: u64,
+) -> anyhow::Result<Option<(CommonInfo, Vec<f32>)>> {
+    let Some(info) = read_chunk(input)? else {
+        return Ok(None);
+    };
+    let info = serde_json::from_slice::<CommonInfo>(&info)?;
+    let mut bed_bytes = vec![0u8; dim as usize * 4];
+    input.read_exact(&mut bed_bytes)?;
+    let bed = bed_bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Ok(Some((info, bed)))
+}
+
+/// Writes every point in the collection to `path`, returning how many there were
+pub async fn export_collection(collection: &Collection, path: &Path) -> anyhow::Result<u64> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let header = PortableHeader {
+        collection: collection.alias_name(),
+        dim: collection.bed_dim(),
+    };
+    write_header(&mut out, &header)?;
+
+    let mut count = 0;
+    let mut offset = None;
+    loop {
+        let (page, next) = collection.export_page(None, offset, PAGE_SIZE).await?;
+        for details in page {
+            write_point(&mut out, &details.info, &details.bed)?;
+            count += 1;
+        }
+        offset = next;
+        if offset.is_none() {
+            break;
+        }
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Upserts everything in an export into the collection (creating it if needed), returning how
+/// many points were loaded. Errors if the export came from a different source/model/distance.
+pub async fn import_collection(collection: &Collection, path: &Path) -> anyhow::Result<u64> {
+    let mut input = BufReader::new(File::open(path)?);
+    let header = read_header(&mut input, path)?;
+    if header.collection != collection.alias_name() || header.dim != collection.bed_dim() {
+        return Err(anyhow::anyhow!(
+            "{:?} holds {} ({} dims), but we're importing into {} ({} dims)",
+            path,
+            header.collection,
+            header.dim,
+            collection.alias_name(),
+            collection.bed_dim()
+        ));
+    }
+    collection.ensure_created().await?;
+
+    let mut count = 0;
+    let mut batch = vec![];
+    while let Some((info, bed)) = read_point(&mut input, header.dim)? {
+        batch.push(DetailedEmbedding {
+            uuid: uri_to_uuid(&info.uri),
+            bed,
+            info,
+        });
+        if batch.len() >= PAGE_SIZE as usize {
+            count += batch.len() as u64;
+            collection.upsert(std::mem::take(&mut batch)).await?;
+        }
+    }
+    count += batch.len() as u64;
+    if !batch.is_empty() {
+        collection.upsert(batch).await?;
+    }
+    Ok(count)
+}
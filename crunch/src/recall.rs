@@ -0,0 +1,135 @@
+use std::{sync::Arc, time::Instant};
+
+use cyclicism::{
+    embed::{embed_async, Embedder, FastEmbedder},
+    exact::{evaluate_recall, recall_at_k, ExactIndex},
+    mydrant::{BedSource, Collection, IndexConfig},
+    repo::get_repository,
+};
+use fastembed::EmbeddingModel;
+use qdrant_client::{qdrant::Distance, Qdrant};
+
+const BED_SOURCE: BedSource = BedSource::HeadlineMain;
+const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
+const DISTANCE: Distance = Distance::Cosine;
+/// Should match whatever the embeddor built the collection with
+const INDEX_CONFIG: IndexConfig = IndexConfig::DEFAULT;
+const NUM_QUERIES: u32 = 200;
+const K: u64 = 10;
+/// Search-time `hnsw_ef` values to try. `None` is whatever `INDEX_CONFIG` says.
+const EF_SWEEP: &[Option<u64>] = &[None, Some(16), Some(32), Some(64), Some(128), Some(256)];
+/// When grading against brute force, every `STRIDE`th archive vector is used as a query
+const STRIDE: usize = 500;
+
+const USAGE: &str = r#"Measure how close the qdrant index gets to the true nearest neighbours.
+
+    cargo run --bin recall -- sweep [version]
+        Real contemporary headlines, qdrant's own exact search, a sweep over hnsw_ef.
+    cargo run --bin recall -- brute <start_year> <end_year> [version]
+        Archive vectors as queries, brute force in Rust as the ground truth."#;
+
+enum Command {
+    Sweep,
+    Brute(u32, u32),
+}
+
+/// Compares the index against exact search for a sample of real contemporary headlines, so
+/// quantization / HNSW settings can be picked with data
+async fn sweep(collection: &Collection, embedder: Arc<dyn Embedder>) -> anyhow::Result<()> {
+    let titles = get_repository(2)
+        .await?
+        .sample_contemporary_titles(NUM_QUERIES)
+        .await?;
+    if titles.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No contemporary articles to use as queries"
+        ));
+    }
+    let beds = embed_async(embedder, titles).await?;
+
+    let mut exact = vec![];
+    let start = Instant::now();
+    for bed in beds.iter() {
+        let uris = collection
+            .top_k_exact(bed.clone(), K)
+            .await?
+            .into_iter()
+            .map(|(info, _)| info.uri)
+            .collect::<Vec<_>>();
+        exact.push(uris);
+    }
+    println!(
+        "exact: {:.1}ms/query",
+        start.elapsed().as_secs_f64() * 1000.0 / beds.len() as f64
+    );
+
+    for ef in EF_SWEEP {
+        let mut total_recall = 0.0;
+        let start = Instant::now();
+        for (bed, truth) in beds.iter().zip(exact.iter()) {
+            let found = collection
+                .top_k_with_ef(bed.clone(), K, *ef)
+                .await?
+                .into_iter()
+                .map(|(info, _)| info.uri)
+                .collect::<Vec<_>>();
+            total_recall += recall_at_k(&found, truth);
+        }
+        let label = match ef {
+            Some(ef) => format!("ef={ef}"),
+            None => "ef=default".to_string(),
+        };
+        println!(
+            "{label}: recall@{K} {:.4}, {:.1}ms/query",
+            total_recall / beds.len() as f64,
+            start.elapsed().as_secs_f64() * 1000.0 / beds.len() as f64
+        );
+    }
+    Ok(())
+}
+
+/// Grades the index against brute-force search over everything published in the year range
+async fn brute(collection: &Collection, start_year: u32, end_year: u32) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let exact = ExactIndex::from_collection(collection, start_year, end_year).await?;
+    println!(
+        "Exported {} vectors from {start_year}-{end_year} in {:.1}s",
+        exact.len(),
+        start.elapsed().as_secs_f64()
+    );
+    let report =
+        evaluate_recall(collection, &exact, start_year, end_year, K as usize, STRIDE).await?;
+    println!("{report}");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    let (version, command) = match args.as_slice() {
+        ["sweep", rest @ ..] => (rest.first().copied(), Command::Sweep),
+        ["brute", start_year, end_year, rest @ ..] => (
+            rest.first().copied(),
+            Command::Brute(start_year.parse()?, end_year.parse()?),
+        ),
+        _ => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+
+    let embedder: Arc<dyn Embedder> = Arc::new(FastEmbedder::try_new(BED_MODEL)?);
+    let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
+    let mut collection = Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant)
+        .with_index_config(INDEX_CONFIG);
+    if let Some(version) = version {
+        collection = collection.with_version(version);
+    }
+    collection.verify().await?;
+
+    match command {
+        Command::Sweep => sweep(&collection, embedder).await,
+        Command::Brute(start_year, end_year) => brute(&collection, start_year, end_year).await,
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{mydrant::Candidate, repo::Repository};
+use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+
+/// A cross-encoder that rescores (query, headline) pairs. Much slower than comparing vectors,
+/// so it should only ever see the top few candidates that came out of qdrant.
+pub struct Reranker {
+    model: TextRerank,
+}
+impl Reranker {
+    pub fn try_new(model: RerankerModel) -> anyhow::Result<Self> {
+        let model =
+            TextRerank::try_new(RerankInitOptions::new(model).with_show_download_progress(true))?;
+        Ok(Self { model })
+    }
+
+    /// Scores each of `documents` against `query`. The returned scores line up with `documents`.
+    pub fn score(&self, query: &str, documents: Vec<&str>) -> anyhow::Result<Vec<f32>> {
+        let mut scores = vec![0.0; documents.len()];
+        for result in self.model.rerank(query, documents, false, None)? {
+            scores[result.index] = result.score;
+        }
+        Ok(scores)
+    }
+
+    /// Rescores the first `top_n` candidates against `query`, looking up their headlines in the repository.
+    /// Returns just those candidates (with `rerank_score` set), sorted by the new score.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        candidates: Vec<Candidate>,
+        top_n: usize,
+        repo: &dyn Repository,
+    ) -> anyhow::Result<Vec<Candidate>> {
+        let mut kept = vec![];
+        let mut headlines = vec![];
+        for candidate in candidates.into_iter().take(top_n) {
+            // If we can't hydrate an article we can't show it anyway
+            let Ok(article) = repo.archive_article(&candidate.info.uri).await else {
+                continue;
+            };
+            headlines.push(article.headline_main);
+            kept.push(candidate);
+        }
+        let scores = self.score(query, headlines.iter().map(|h| h.as_str()).collect())?;
+        for (candidate, score) in kept.iter_mut().zip(scores) {
+            candidate.rerank_score = Some(score);
+        }
+        kept.sort_by(|a, b| b.relevance().total_cmp(&a.relevance()));
+        Ok(kept)
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use reqwest::{header::RETRY_AFTER, Response};
+
+/// Parses `Retry-After`, which is either a number of seconds or an HTTP date
+pub fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let when = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (when.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// Exponential backoff for the NYT apis: starts at `base_secs`, doubles on each retry up to
+/// `max_secs`, and waits longer if the server asked for longer
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub base_secs: u64,
+    pub max_secs: u64,
+}
+impl Backoff {
+    /// How long to wait before retry number `retries` (0 for the first)
+    pub fn wait(&self, retries: u32, after: Option<Duration>) -> Duration {
+        let backoff = Duration::from_secs((self.base_secs << retries.min(16)).min(self.max_secs));
+        after.unwrap_or(backoff).max(backoff)
+    }
+}
@@ -1,75 +1,138 @@
-use chrono::{Datelike, NaiveDate};
-use cyclicism::get_date;
-use std::{env, time::Duration};
+use cyclicism::{
+    archive::{ArchiveClient, BACKOFF, MAX_RETRIES_PER_MONTH},
+    compress::{find_scrape, Compression},
+    get_date,
+    manifest::{verify_month, write_atomic, Manifest, MonthProblem},
+};
+use std::time::Duration;
 
-enum MonthStatus {
-    AlreadyExists,
-    DownloadFailed,
-    WriteFailed,
-    Downloaded,
+// Don't get rate-limited
+const SLEEP_SECS: u64 = 15;
+/// How new scrapes get stored. Existing files are read whatever they are. Zstd is about a tenth
+/// the size but slow to write, so it's opt-in (or convert later with `compress`).
+const COMPRESSION: Compression = Compression::Plain;
+
+const USAGE: &str = r#"Download the NYT archive into scrapes/, one file per month.
+
+    cargo run --bin scraper                   scrape whatever's missing (from NYT_BASE_URL if set)
+    cargo run --bin scraper -- verify         recheck every file against scrapes/manifest.json
+    cargo run --bin scraper -- verify --repair
+        also delete corrupt files (so the next scrape refetches them) and add unlisted ones
+    cargo run --bin scraper -- compress <plain|gzip|zstd>
+        rewrite every existing scrape in that format"#;
+
+async fn scrape_data(archive: &ArchiveClient) -> anyhow::Result<()> {
+    std::fs::create_dir_all("scrapes")?;
+    let mut manifest = Manifest::load()?;
+    for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
+        for month in 1..=12 {
+            let date = get_date(year, month);
+            let downloaded = archive
+                .fetch_month(
+                    date,
+                    COMPRESSION,
+                    &mut manifest,
+                    BACKOFF,
+                    MAX_RETRIES_PER_MONTH,
+                )
+                .await?;
+            if downloaded {
+                tokio::time::sleep(Duration::from_secs(SLEEP_SECS)).await;
+            }
+        }
+        println!("Finished {year}");
+    }
+    Ok(())
 }
 
-/// Gets the json and writes it to the file. Does NOT sleep.
-/// Returns Ok(false) if the file already exists.
-/// Returns Ok(true) if we successfully
-async fn handle_month(date: NaiveDate) -> MonthStatus {
-    let api_key = env::var("NYT_API_KEY").unwrap();
-    let path = cyclicism::get_json_path(date);
-    if path.exists() {
-        return MonthStatus::AlreadyExists;
+/// Rechecks every month, optionally fixing what can be fixed without the network
+fn verify(repair: bool) -> anyhow::Result<()> {
+    let mut manifest = Manifest::load()?;
+    let mut num_ok = 0;
+    let mut num_bad = 0;
+    for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
+        for month in 1..=12 {
+            let date = get_date(year, month);
+            let Some(problem) = verify_month(&manifest, date)? else {
+                num_ok += 1;
+                continue;
+            };
+            num_bad += 1;
+            println!("{year}/{month}: {problem}");
+            if !repair {
+                continue;
+            }
+            match problem {
+                MonthProblem::Unlisted(entry) => manifest.insert(date, entry),
+                MonthProblem::MissingFile => manifest.remove(date),
+                problem if problem.is_corrupt() => {
+                    if let Some((path, _)) = find_scrape(date) {
+                        std::fs::remove_file(path)?;
+                    }
+                    manifest.remove(date);
+                }
+                _ => {}
+            }
+        }
     }
-    let Ok(resp) = reqwest::get(format!(
-        "https://api.nytimes.com/svc/archive/v1/{}/{}.json?api-key={}",
-        date.year_ce().1,
-        date.month0() + 1,
-        api_key
-    ))
-    .await
-    else {
-        return MonthStatus::DownloadFailed;
-    };
-    let Ok(body) = resp.text().await else {
-        return MonthStatus::DownloadFailed;
-    };
-    if tokio::fs::write(cyclicism::get_json_path(date), body)
-        .await
-        .is_err()
-    {
-        return MonthStatus::WriteFailed;
+    if repair {
+        manifest.save()?;
     }
-    MonthStatus::Downloaded
+    println!("{num_ok} months ok, {num_bad} with problems");
+    Ok(())
 }
 
-async fn scrape_data() {
-    // Don't get rate-limited
-    const SLEEP_SECS: u64 = 15;
-    let mut total_retries_left = 100;
+/// Rewrites every scrape in `target`, checking the new file reads back the same before
+/// deleting the old one. The manifest describes the json, so it doesn't change.
+fn compress_all(target: Compression) -> anyhow::Result<()> {
+    let mut num_converted = 0;
+    let mut bytes_before = 0;
+    let mut bytes_after = 0;
     for year in cyclicism::START_YEAR..=cyclicism::END_YEAR {
-        let mut month = 1;
-        while month <= 12 {
-            if total_retries_left <= 0 {
-                panic!("Ran out of retries trying to scrape data :/");
-            }
+        for month in 1..=12 {
             let date = get_date(year, month);
-            match handle_month(date).await {
-                MonthStatus::AlreadyExists => {
-                    month += 1;
-                }
-                MonthStatus::DownloadFailed | MonthStatus::WriteFailed => {
-                    tokio::time::sleep(Duration::from_secs(SLEEP_SECS)).await;
-                    total_retries_left -= 1;
-                }
-                MonthStatus::Downloaded => {
-                    tokio::time::sleep(Duration::from_secs(SLEEP_SECS)).await;
-                    month += 1;
-                }
+            let Some((path, current)) = find_scrape(date) else {
+                continue;
+            };
+            if current == target {
+                continue;
             }
+            let stored = std::fs::read(&path)?;
+            let json = current.decompress(&stored)?;
+            let converted = target.compress(&json)?;
+            if target.decompress(&converted)? != json {
+                return Err(anyhow::anyhow!(
+                    "{:?} didn't survive a round trip through {:?}",
+                    path,
+                    target
+                ));
+            }
+            write_atomic(&target.path_for(date), &converted)?;
+            std::fs::remove_file(&path)?;
+            num_converted += 1;
+            bytes_before += stored.len();
+            bytes_after += converted.len();
         }
         println!("Finished {year}");
     }
+    println!(
+        "Converted {num_converted} files, {} MB -> {} MB",
+        bytes_before / 1_000_000,
+        bytes_after / 1_000_000
+    );
+    Ok(())
 }
 
 #[tokio::main]
-async fn main() {
-    scrape_data().await;
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => scrape_data(&ArchiveClient::from_env()?).await?,
+        ["verify"] => verify(false)?,
+        ["verify", "--repair"] => verify(true)?,
+        ["compress", name] => compress_all(Compression::from_name(name)?)?,
+        _ => println!("{USAGE}"),
+    }
+    Ok(())
 }
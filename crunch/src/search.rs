@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use reqwest::{Client, StatusCode};
+
+use crate::{
+    lenient::{decode_article, DocOutcome, DocStats},
+    nyt::ScrapedArticle,
+    retry::{retry_after, Backoff},
+};
+
+/// The Article Search API gives 10 docs a page
+pub const SEARCH_PAGE_SIZE: u32 = 10;
+/// ...and won't go past page 100, so a query can only ever see 1000 docs
+pub const SEARCH_MAX_PAGES: u32 = 100;
+/// It allows 5 requests a minute
+pub const SEARCH_SLEEP_SECS: u64 = 12;
+const MAX_RETRIES: u32 = 6;
+const BACKOFF: Backoff = Backoff {
+    base_secs: SEARCH_SLEEP_SECS,
+    max_secs: 600,
+};
+
+/// An Article Search API query. `begin` and `end` are inclusive.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub begin: NaiveDate,
+    pub end: NaiveDate,
+    /// A Lucene filter query, like `section_name:("Sports")`
+    pub fq: Option<String>,
+}
+impl SearchQuery {
+    pub fn day(date: NaiveDate) -> Self {
+        Self {
+            begin: date,
+            end: date,
+            fq: None,
+        }
+    }
+
+    pub fn with_fq(mut self, fq: Option<String>) -> Self {
+        self.fq = fq;
+        self
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SearchMeta {
+    hits: u32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SearchResponse {
+    meta: SearchMeta,
+    docs: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SearchJson {
+    response: SearchResponse,
+}
+
+/// One page of results, already mapped to `ScrapedArticle`s
+pub struct SearchPage {
+    /// How many docs match the whole query, not just this page
+    pub hits: u32,
+    pub articles: Vec<ScrapedArticle>,
+    pub stats: DocStats,
+}
+
+/// Gets one page (0-indexed), retrying with backoff on rate limits, server errors and bodies
+/// that don't parse
+pub async fn search_page(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    query: &SearchQuery,
+    page: u32,
+) -> anyhow::Result<SearchPage> {
+    let begin = query.begin.format("%Y%m%d").to_string();
+    let end = query.end.format("%Y%m%d").to_string();
+    let page_str = page.to_string();
+    let mut params = vec![
+        ("api-key", api_key),
+        ("begin_date", begin.as_str()),
+        ("end_date", end.as_str()),
+        ("sort", "oldest"),
+        ("page", page_str.as_str()),
+    ];
+    if let Some(fq) = query.fq.as_ref() {
+        params.push(("fq", fq.as_str()));
+    }
+
+    let mut retries = 0;
+    let json = loop {
+        let resp = client
+            .get(format!("{base_url}/svc/search/v2/articlesearch.json"))
+            .query(&params)
+            .send()
+            .await;
+        let (reason, after) = match resp {
+            Ok(resp) if resp.status().is_success() => match resp.bytes().await {
+                // A 200 can still be cut off, same as in the scraper
+                Ok(body) => match serde_json::from_slice::<SearchJson>(&body) {
+                    Ok(json) => break json,
+                    Err(e) => (format!("body isn't a search response: {e}"), None),
+                },
+                Err(e) => (format!("couldn't read body: {e}"), None),
+            },
+            Ok(resp)
+                if resp.status() == StatusCode::TOO_MANY_REQUESTS
+                    || resp.status().is_server_error() =>
+            {
+                (format!("got {}", resp.status()), retry_after(&resp))
+            }
+            Ok(resp) => {
+                return Err(anyhow::anyhow!(
+                    "Article Search got {}: {}",
+                    resp.status(),
+                    resp.text().await.unwrap_or_default()
+                ))
+            }
+            Err(e) => (format!("request failed: {e}"), None),
+        };
+        if retries >= MAX_RETRIES {
+            return Err(anyhow::anyhow!(
+                "Gave up on {:?} page {page} after {retries} retries, last: {reason}",
+                query
+            ));
+        }
+        let wait = BACKOFF.wait(retries, after);
+        println!("{reason}, retrying in {}s", wait.as_secs());
+        tokio::time::sleep(wait).await;
+        retries += 1;
+    };
+
+    let mut stats = DocStats::default();
+    let mut articles = vec![];
+    for doc in json.response.docs.iter() {
+        match decode_article(doc) {
+            DocOutcome::Parsed(article) => {
+                stats.parsed += 1;
+                articles.push(article);
+            }
+            DocOutcome::Coerced(article) => {
+                stats.coerced += 1;
+                articles.push(article);
+            }
+            DocOutcome::Skipped(e) => {
+                stats.skipped += 1;
+                println!("Skipping a search result: {e}");
+            }
+        }
+    }
+    Ok(SearchPage {
+        hits: json.response.meta.hits,
+        articles,
+        stats,
+    })
+}
+
+/// Just the number of docs matching `query`
+pub async fn search_hits(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    query: &SearchQuery,
+) -> anyhow::Result<u32> {
+    Ok(search_page(client, base_url, api_key, query, 0).await?.hits)
+}
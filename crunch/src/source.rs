@@ -0,0 +1,67 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use uuid::Uuid;
+
+use crate::nyt::uri_to_uuid;
+
+/// A contemporary article from any outlet, in the shape the matching pipeline needs. Whatever
+/// the source's own format is, it gets turned into one of these.
+#[derive(Debug, Clone)]
+pub struct NewsArticle {
+    /// `NewsSource::name` of where it came from
+    pub source: String,
+    /// Stable across fetches, and what the uuid comes from. NYT's `nyt://...` uris, or the
+    /// link for feed entries.
+    pub uri: String,
+    pub url: String,
+    pub headline: String,
+    /// Plain text, no html
+    pub summary: String,
+    pub published_at: DateTime<FixedOffset>,
+    pub section: String,
+    /// NYT-only (it's what the junk rules and the frontend treat as `type_of_material`), empty
+    /// for everything else
+    pub subsection: String,
+    /// Article, Interactive, Video... if the source says. Empty otherwise.
+    pub kind: String,
+    /// NYT-only too
+    pub kicker: String,
+    /// News, Op-Ed, Obituary (Obit)... in the archive's `type_of_material` vocabulary. NYT's
+    /// `material_type_facet`, often empty, and always empty for other sources.
+    pub type_of_material: String,
+    pub tags: Vec<String>,
+    pub image: Option<NewsImage>,
+}
+impl NewsArticle {
+    pub fn uuid(&self) -> Uuid {
+        uri_to_uuid(&self.uri)
+    }
+
+    /// (year, month, day) in the article's own timezone, which is how the contemporary tables
+    /// are keyed
+    pub fn date_parts(&self) -> (u32, u32, u32) {
+        let date = self.published_at.date_naive();
+        (date.year_ce().1, date.month(), date.day())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewsImage {
+    pub url: String,
+    pub caption: Option<String>,
+    /// NYT crop name, like "Super Jumbo"
+    pub format: Option<String>,
+    /// "image" unless the source says otherwise
+    pub type_: String,
+    pub subtype: Option<String>,
+}
+
+/// Somewhere articles come from. The NYT (`nyt::NytSource`) is the original, `feed::FeedSource`
+/// covers anything with an RSS or Atom feed.
+#[async_trait::async_trait]
+pub trait NewsSource: Send + Sync {
+    /// Short and stable, it's stored with every article (like "nyt")
+    fn name(&self) -> &str;
+
+    /// What's current, like a homepage or the top of a feed
+    async fn latest(&self) -> anyhow::Result<Vec<NewsArticle>>;
+}
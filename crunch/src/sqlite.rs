@@ -0,0 +1,527 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use chrono::Datelike;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    Row, SqlitePool,
+};
+
+use crate::{
+    nyt::{
+        clean_snippet, parse_pub_date, FrontendArticle, FrontendImage, ScrapedArticle,
+        StoredContemporary,
+    },
+    repo::{read_migrations, stored_contemporary_from_row, Repository, UNPROMOTED_CONTEMPORARY},
+    source::NewsArticle,
+};
+
+/// A `Repository` in a single local file (or in memory with `sqlite::memory:`), for running the
+/// pipeline on a laptop and for tests. Uses the same tables as Postgres, see `migrations_sqlite/`.
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+impl SqliteRepository {
+    /// e.g `sqlite://cyclicism.db`. The file is created if it doesn't exist.
+    /// With `sqlite::memory:` every connection gets its own database, so use one connection.
+    pub async fn connect(url: &str, max_connections: u32) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for SqliteRepository {
+    async fn apply_migrations(&self) -> anyhow::Result<()> {
+        for statement in read_migrations(Path::new("migrations_sqlite")).await? {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn upsert_scraped(&self, article: &ScrapedArticle) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO scraped_article (
+                uri, web_url, snippet, print_page, print_section, source, pub_date, document_type, news_desk, section_name, type_of_material
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+            )
+            ON CONFLICT (uri) DO UPDATE
+            SET web_url = $2, snippet = $3, print_page = $4, print_section = $5, source = $6, pub_date = $7, document_type = $8, news_desk = $9, section_name = $10, type_of_material = $11
+            "#,
+        )
+        .bind(&article.uri)
+        .bind(&article.web_url)
+        .bind(&article.snippet)
+        .bind(&article.print_page)
+        .bind(&article.print_section)
+        .bind(&article.source)
+        .bind(&article.pub_date)
+        .bind(&article.document_type)
+        .bind(&article.news_desk)
+        .bind(&article.section_name)
+        .bind(&article.type_of_material)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO scraped_headline (
+                uri, main, kicker, content_kicker, print_headline, name, seo, sub
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8
+            )
+            ON CONFLICT (uri) DO UPDATE
+            SET main = $2, kicker = $3, content_kicker = $4, print_headline = $5, name = $6, seo = $7, sub = $8
+            "#,
+        )
+        .bind(&article.uri)
+        .bind(&article.headline.main)
+        .bind(&article.headline.kicker)
+        .bind(&article.headline.content_kicker)
+        .bind(&article.headline.print_headline)
+        .bind(&article.headline.name)
+        .bind(&article.headline.seo)
+        .bind(&article.headline.sub)
+        .execute(&mut *tx)
+        .await?;
+        if let Some(first_media) = article.multimedia.first() {
+            sqlx::query(
+                r#"
+                INSERT INTO scraped_multimedia (
+                    uri, rank, subtype, caption, credit, type_, url, height, width, crop_name
+                ) VALUES (
+                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+                )
+                ON CONFLICT (uri) DO UPDATE
+                SET rank = $2, subtype = $3, caption = $4, credit = $5, type_ = $6, url = $7, height = $8, width = $9, crop_name = $10
+                "#,
+            )
+            .bind(&article.uri)
+            .bind(first_media.rank as i32)
+            .bind(&first_media.subtype)
+            .bind(&first_media.caption)
+            .bind(&first_media.credit)
+            .bind(&first_media.type_)
+            .bind(&first_media.url)
+            .bind(first_media.height as i32)
+            .bind(first_media.width as i32)
+            .bind(&first_media.crop_name)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn upsert_contemporary(&self, article: &NewsArticle) -> anyhow::Result<()> {
+        let (yy, mm, dd) = article.date_parts();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO contemporary_article
+                (uri, url, yy, mm, dd, title, abstract, section, subsection, item_type, kicker)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (uri) DO UPDATE
+            SET url = $2, yy = $3, mm = $4, dd = $5, title = $6, abstract = $7, section = $8, subsection = $9, item_type = $10, kicker = $11
+            "#,
+        )
+        .bind(&article.uri)
+        .bind(&article.url)
+        .bind(yy as i32)
+        .bind(mm as i32)
+        .bind(dd as i32)
+        .bind(&article.headline)
+        .bind(&article.summary)
+        .bind(&article.section)
+        .bind(&article.subsection)
+        .bind(&article.kind)
+        .bind(&article.kicker)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO contemporary_source (uri, source)
+            VALUES ($1, $2)
+            ON CONFLICT (uri) DO UPDATE
+            SET source = $2
+            "#,
+        )
+        .bind(&article.uri)
+        .bind(&article.source)
+        .execute(&mut *tx)
+        .await?;
+        if let Some(image) = article.image.as_ref() {
+            sqlx::query(
+                r#"
+                INSERT INTO contemporary_multimedia
+                    (uri, url, rank, format, type_, subtype, caption)
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (uri) DO UPDATE
+                SET url = $2, rank = $3, format = $4, type_ = $5, subtype = $6, caption = $7
+                "#,
+            )
+            .bind(&article.uri)
+            .bind(&image.url)
+            .bind(0)
+            .bind(&image.format)
+            .bind(&image.type_)
+            .bind(&image.subtype)
+            .bind(&image.caption)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn archive_article(&self, uri: &str) -> anyhow::Result<FrontendArticle> {
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT a.web_url, a.snippet, a.print_section, a.pub_date, a.document_type, a.news_desk,
+                a.type_of_material, h.main, m.url, m.caption
+            FROM scraped_article a
+            JOIN scraped_headline h ON h.uri = a.uri
+            LEFT JOIN scraped_multimedia m ON m.uri = a.uri
+            WHERE a.uri = $1
+            "#,
+        )
+        .bind(uri)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Err(anyhow::anyhow!("Couldn't select article row"));
+        };
+        let pub_date: String = row.get(3);
+        let naive_date = parse_pub_date(&pub_date);
+        let image = row.get::<Option<String>, _>(8).map(|url| FrontendImage {
+            url,
+            caption: row.get(9),
+        });
+        Ok(FrontendArticle {
+            uri: uri.to_string(),
+            web_url: row.get(0),
+            headline_main: row.get(7),
+            snippet: clean_snippet(row.get(1)),
+            year: naive_date.year_ce().1,
+            month: naive_date.month0() + 1,
+            day: naive_date.day(),
+            image,
+            print_section: row.get(2),
+            document_type: row.get(4),
+            news_desk: row.get(5),
+            type_of_material: row.get(6),
+        })
+    }
+
+    async fn has_archive_article(&self, uri: &str) -> anyhow::Result<bool> {
+        Ok(sqlx::query(
+            r#"
+            SELECT 1 FROM scraped_article WHERE uri = $1
+            "#,
+        )
+        .bind(uri)
+        .fetch_optional(&self.pool)
+        .await?
+        .is_some())
+    }
+
+    async fn contemporary_article(&self, uri: &str) -> anyhow::Result<FrontendArticle> {
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT a.url, a.yy, a.mm, a.dd, a.title, a.abstract, a.section, a.subsection,
+                a.item_type, m.url, m.caption
+            FROM contemporary_article a
+            LEFT JOIN contemporary_multimedia m ON m.uri = a.uri
+            WHERE a.uri = $1
+            "#,
+        )
+        .bind(uri)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Err(anyhow::anyhow!("Couldn't select contemporary article row"));
+        };
+        let image = row.get::<Option<String>, _>(9).map(|url| FrontendImage {
+            url,
+            caption: row.get(10),
+        });
+        Ok(FrontendArticle {
+            uri: uri.to_string(),
+            web_url: row.get(0),
+            headline_main: row.get(4),
+            snippet: row.get(5),
+            year: row.get::<i32, _>(1) as u32,
+            month: row.get::<i32, _>(2) as u32,
+            day: row.get::<i32, _>(3) as u32,
+            image,
+            print_section: None,
+            document_type: row.get(8),
+            news_desk: row.get(6),
+            type_of_material: row.get(7),
+        })
+    }
+
+    async fn contemporary_uris_on(
+        &self,
+        year: u32,
+        month: u32,
+        day: u32,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT uri
+            FROM contemporary_article
+            WHERE yy = $1 AND mm = $2 AND dd = $3
+            "#,
+        )
+        .bind(year as i32)
+        .bind(month as i32)
+        .bind(day as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect())
+    }
+
+    async fn insert_combo(
+        &self,
+        contemporary_uri: &str,
+        past_uri: &str,
+        score: f32,
+        rerank_score: Option<f32>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO combos (contemporary_uri, past_uri, score, rerank_score)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (contemporary_uri, past_uri) DO UPDATE
+            SET score = $3, rerank_score = $4
+            "#,
+        )
+        .bind(contemporary_uri)
+        .bind(past_uri)
+        .bind(score)
+        .bind(rerank_score)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_unmatched(
+        &self,
+        contemporary_uri: &str,
+        best_score: Option<f32>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO unmatched (contemporary_uri, best_score)
+            VALUES ($1, $2)
+            ON CONFLICT (contemporary_uri) DO UPDATE
+            SET best_score = $2, time_checked = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(contemporary_uri)
+        .bind(best_score)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn is_matched(&self, contemporary_uri: &str) -> anyhow::Result<bool> {
+        let count: i64 = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM combos WHERE contemporary_uri = $1) +
+                (SELECT COUNT(*) FROM unmatched WHERE contemporary_uri = $1)"#,
+        )
+        .bind(contemporary_uri)
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+        Ok(count > 0)
+    }
+
+    async fn is_unmatched(&self, contemporary_uri: &str) -> anyhow::Result<bool> {
+        Ok(sqlx::query(
+            r#"
+            SELECT 1 FROM unmatched WHERE contemporary_uri = $1
+            "#,
+        )
+        .bind(contemporary_uri)
+        .fetch_optional(&self.pool)
+        .await?
+        .is_some())
+    }
+
+    async fn combo_uris(&self, contemporary_uri: &str) -> anyhow::Result<Vec<String>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT past_uri
+            FROM combos
+            WHERE contemporary_uri = $1
+            ORDER BY rerank_score DESC NULLS LAST, score DESC
+            "#,
+        )
+        .bind(contemporary_uri)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect())
+    }
+
+    async fn sample_contemporary_titles(&self, n: u32) -> anyhow::Result<Vec<String>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT title
+            FROM contemporary_article
+            ORDER BY random()
+            LIMIT $1
+            "#,
+        )
+        .bind(n as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect())
+    }
+
+    async fn unpromoted_contemporary(&self) -> anyhow::Result<Vec<StoredContemporary>> {
+        Ok(sqlx::query(UNPROMOTED_CONTEMPORARY)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .filter_map(stored_contemporary_from_row)
+            .collect())
+    }
+
+    async fn mark_promoted(&self, uri: &str, from_archive: bool) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO promoted (uri, from_archive)
+            VALUES ($1, $2)
+            ON CONFLICT (uri) DO UPDATE
+            SET from_archive = $2
+            "#,
+        )
+        .bind(uri)
+        .bind(from_archive)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn archive_headlines(&self, uris: &[String]) -> anyhow::Result<HashMap<String, String>> {
+        // No array binds here either, and dedup asks for a year at a time, so at least keep it
+        // to one transaction
+        let mut tx = self.pool.begin().await?;
+        let mut headlines = HashMap::new();
+        for uri in uris {
+            if let Some(row) = sqlx::query(
+                r#"
+                SELECT main
+                FROM scraped_headline
+                WHERE uri = $1
+                "#,
+            )
+            .bind(uri)
+            .fetch_optional(&mut *tx)
+            .await?
+            {
+                headlines.insert(uri.clone(), row.get(0));
+            }
+        }
+        tx.commit().await?;
+        Ok(headlines)
+    }
+
+    async fn canonical_uris(&self, uris: &[String]) -> anyhow::Result<HashMap<String, String>> {
+        // No array binds in SQLite, and these lists are only ever a page of results long
+        let mut canonical = HashMap::new();
+        for uri in uris {
+            if let Some(row) = sqlx::query(
+                r#"
+                SELECT canonical_uri
+                FROM canonical_uri
+                WHERE uri = $1
+                "#,
+            )
+            .bind(uri)
+            .fetch_optional(&self.pool)
+            .await?
+            {
+                canonical.insert(uri.clone(), row.get(0));
+            }
+        }
+        Ok(canonical)
+    }
+
+    async fn store_canonical_uris(&self, mapping: &[(String, String)]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for (uri, canonical_uri) in mapping {
+            sqlx::query(
+                r#"
+                INSERT INTO canonical_uri (uri, canonical_uri)
+                VALUES ($1, $2)
+                ON CONFLICT (uri) DO UPDATE
+                SET canonical_uri = $2, time_added = CURRENT_TIMESTAMP
+                "#,
+            )
+            .bind(uri)
+            .bind(canonical_uri)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn replace_current(&self, uris: &[String]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM current
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+        for (ix, uri) in uris.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO current (uri, rank)
+                VALUES ($1, $2)
+                "#,
+            )
+            .bind(uri)
+            .bind(ix as i32)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn current_uris(&self) -> anyhow::Result<Vec<String>> {
+        Ok(sqlx::query(
+            r#"
+            SELECT uri
+            FROM current
+            ORDER BY rank
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect())
+    }
+}
@@ -0,0 +1,39 @@
+use uuid::Uuid;
+
+use crate::mydrant::{Candidate, Collection, CommonInfo, DetailedEmbedding};
+
+/// The operations the pipeline needs from wherever the archive vectors live. `Collection` does
+/// them with qdrant, `FlatStore` in-process, so small deployments don't need a qdrant container.
+#[async_trait::async_trait]
+pub trait VectorStore: Send + Sync {
+    async fn ensure_created(&self) -> anyhow::Result<()>;
+    async fn upsert(&self, data: Vec<DetailedEmbedding>) -> anyhow::Result<()>;
+    async fn overwrite_payload(&self, uuid: Uuid, info: CommonInfo) -> anyhow::Result<()>;
+    /// The `k` closest points to `bed`, best first
+    async fn top_k(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<(CommonInfo, f32)>>;
+    /// Like `top_k`, but with vectors attached so the results can be diversified
+    async fn top_k_candidates(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<Candidate>>;
+}
+
+#[async_trait::async_trait]
+impl VectorStore for Collection {
+    async fn ensure_created(&self) -> anyhow::Result<()> {
+        Collection::ensure_created(self).await
+    }
+
+    async fn upsert(&self, data: Vec<DetailedEmbedding>) -> anyhow::Result<()> {
+        Collection::upsert(self, data).await
+    }
+
+    async fn overwrite_payload(&self, uuid: Uuid, info: CommonInfo) -> anyhow::Result<()> {
+        Collection::overwrite_payload(self, uuid, info).await
+    }
+
+    async fn top_k(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<(CommonInfo, f32)>> {
+        Collection::top_k(self, bed, k).await
+    }
+
+    async fn top_k_candidates(&self, bed: Vec<f32>, k: u64) -> anyhow::Result<Vec<Candidate>> {
+        Collection::top_k_candidates(self, bed, k).await
+    }
+}
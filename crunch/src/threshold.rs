@@ -0,0 +1,49 @@
+use crate::mydrant::Candidate;
+
+/// How good a candidate has to be before we're willing to call it a historical parallel.
+/// Qdrant scores and cross-encoder scores are distributed nothing alike, so each gets its own
+/// floor, picked by whether the candidate was reranked.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreFloor {
+    /// Candidates that weren't reranked are dropped if their qdrant `score` is below this
+    pub retrieval: Option<f32>,
+    /// Reranked candidates are dropped if their `relevance` (the cross-encoder logit, squashed
+    /// to 0-1) is below this
+    pub rerank: Option<f32>,
+    /// Candidates scoring below `relative_to_best * best_score` are dropped, comparing
+    /// `relevance`
+    pub relative_to_best: Option<f32>,
+}
+impl ScoreFloor {
+    /// Lets everything through
+    pub const OFF: Self = Self {
+        retrieval: None,
+        rerank: None,
+        relative_to_best: None,
+    };
+
+    /// Whether `candidate` clears the absolute floor for its kind of score
+    pub fn clears(&self, candidate: &Candidate) -> bool {
+        let floor = match candidate.rerank_score {
+            Some(_) => self.rerank,
+            None => self.retrieval,
+        };
+        floor.is_none_or(|floor| candidate.relevance() >= floor)
+    }
+
+    /// Drops every candidate below the floor. An empty result means nothing in the archive was close enough.
+    pub fn apply(&self, candidates: Vec<Candidate>) -> Vec<Candidate> {
+        let Some(best) = candidates
+            .iter()
+            .map(|c| c.relevance())
+            .max_by(|a, b| a.total_cmp(b))
+        else {
+            return candidates;
+        };
+        let relative = self.relative_to_best.map(|r| r * best).unwrap_or(f32::MIN);
+        candidates
+            .into_iter()
+            .filter(|c| self.clears(c) && c.relevance() >= relative)
+            .collect()
+    }
+}
@@ -1,35 +1,32 @@
 use cyclicism::{
-    mydrant::{BedSource, Collection},
-    nyt::{get_current_homepage, ContemporaryArticle},
-    pg::get_pg_pool,
+    dedup::collapse_candidates,
+    diversify::{diversify, Diversity},
+    embed::{embed_one, CachedEmbedder, Embedder, FastEmbedder},
+    feed::FeedSource,
+    junk::{JunkFilter, JunkRules},
+    mydrant::{BedSource, Collection, IndexConfig},
+    nyt::NytSource,
+    repo::{get_repository, Repository},
+    rerank::Reranker,
+    source::{NewsArticle, NewsSource},
+    threshold::ScoreFloor,
 };
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use fastembed::{EmbeddingModel, RerankerModel};
 use qdrant_client::{qdrant::Distance, Qdrant};
-use sqlx::Row;
-use sqlx::{Pool, Postgres};
-use std::{env, sync::Arc};
+use std::{path::Path, sync::Arc};
 
 /// Given a list of contemporary articles, filter down to only those without combos
+/// (and that we haven't already decided have no good match)
 async fn filter_new_articles<'a>(
-    all_articles: &'a Vec<ContemporaryArticle>,
-    pg: &Pool<Postgres>,
-) -> anyhow::Result<Vec<&'a ContemporaryArticle>> {
+    all_articles: &'a Vec<NewsArticle>,
+    repo: &dyn Repository,
+) -> anyhow::Result<Vec<&'a NewsArticle>> {
     let mut new_ones = vec![];
     for article in all_articles {
-        let Ok(count_row) = sqlx::query(
-            r#"
-            SELECT COUNT(*)
-            FROM combos
-            WHERE contemporary_uri = $1"#,
-        )
-        .bind(&article.uri)
-        .fetch_one(pg)
-        .await
-        else {
+        let Ok(matched) = repo.is_matched(&article.uri).await else {
             continue;
         };
-        let count: i64 = count_row.get(0);
-        if count > 0 {
+        if matched {
             continue;
         }
         new_ones.push(article);
@@ -37,86 +34,151 @@ async fn filter_new_articles<'a>(
     Ok(new_ones)
 }
 
+/// Whether the NYT's top stories are one of the sources
+const USE_NYT: bool = true;
+/// (name, url) of RSS or Atom feeds to match too. Names get stored with the articles, so don't
+/// change them once they're in use.
+const FEEDS: &[(&str, &str)] = &[];
 const BED_SOURCE: BedSource = BedSource::HeadlineMain;
-const BED_DIM: u64 = 1024;
 const BED_MODEL: EmbeddingModel = EmbeddingModel::GTELargeENV15Q;
 const DISTANCE: Distance = Distance::Cosine;
+/// How qdrant should store and search the collection, see `IndexConfig`
+const INDEX_CONFIG: IndexConfig = IndexConfig::DEFAULT;
+/// Shared with the embeddor, so titles it has already seen skip the model
+const BED_CACHE_DIR: &str = "bed_cache";
+/// Contemporary articles matching these don't get combos, see `JunkRules`
+const JUNK_RULES: JunkRules = JunkRules::DEFAULT;
+/// How many combos each contemporary article should end up with
+const NUM_COMBOS: usize = 10;
+/// How many candidates to pull from qdrant before diversifying down to `NUM_COMBOS`
+const NUM_CANDIDATES: u64 = 50;
+const DIVERSITY: Diversity = Diversity::Mmr { lambda: 0.7 };
+/// Set to `None` to skip the cross-encoder and go straight from qdrant scores to combos
+const RERANK_MODEL: Option<RerankerModel> = Some(RerankerModel::BGERerankerBase);
+/// How many of the qdrant candidates get rescored by the cross-encoder
+const RERANK_TOP_N: usize = 30;
+/// Candidates below this are too weak to show, even if they're the best we've got. The rerank
+/// floor only applies with `RERANK_MODEL`, the retrieval one only without it.
+const SCORE_FLOOR: ScoreFloor = ScoreFloor {
+    // Cosine similarity with GTE large
+    retrieval: Some(0.75),
+    // Sigmoid of the BGE logit, so 0.5 is a logit of 0. Loose headline parallels land well
+    // below that.
+    rerank: Some(0.1),
+    relative_to_best: Some(0.9),
+};
 
 /// Given all of the current articles, embed and add combos only for those that need it
 async fn update_combos(
-    current_articles: &Vec<ContemporaryArticle>,
-    pg: &Pool<Postgres>,
+    current_articles: &Vec<NewsArticle>,
+    repo: &dyn Repository,
+    embedder: Arc<dyn Embedder>,
+    diversity: Diversity,
+    reranker: Option<&Reranker>,
+    floor: ScoreFloor,
+    junk: &JunkFilter,
 ) -> anyhow::Result<()> {
     let qdrant = Qdrant::from_url("http://localhost:6334").build()?;
-    let collection = Arc::new(Collection::new(
-        BED_SOURCE, BED_DIM, BED_MODEL, DISTANCE, qdrant,
-    ));
-    let loaded_model = Arc::new(TextEmbedding::try_new(
-        InitOptions::new(BED_MODEL).with_show_download_progress(true),
-    )?);
-    let unseen = filter_new_articles(&current_articles, pg).await?;
+    let collection = Arc::new(
+        Collection::new(BED_SOURCE, embedder.as_ref(), DISTANCE, qdrant)
+            .with_index_config(INDEX_CONFIG),
+    );
+    collection.verify().await?;
+    let unseen = filter_new_articles(current_articles, repo).await?;
     println!("unseen: {} vs {}", current_articles.len(), unseen.len());
     for article in unseen {
-        let bed = loaded_model
-            .embed(vec![article.title.clone()], None)?
-            .into_iter()
-            .next()
-            .unwrap();
-        let scored_infos = collection.top_k(bed, 10).await?;
-        for (info, score) in scored_infos {
-            sqlx::query(
-                r#"
-            INSERT INTO combos (contemporary_uri, past_uri, score)
-            VALUES ($1, $2, $3)
-            "#,
+        if junk.check_news(article).is_some() {
+            // So it isn't checked (and counted) again next run. Junk never gets upserted, so
+            // this doesn't show up as "no parallel" anywhere.
+            repo.insert_unmatched(&article.uri, None).await.ok();
+            continue;
+        }
+        let bed = embed_one(embedder.clone(), article.headline.clone()).await?;
+        let mut candidates = collection.top_k_candidates(bed, NUM_CANDIDATES).await?;
+        candidates = collapse_candidates(candidates, repo).await?;
+        if let Some(reranker) = reranker {
+            candidates = reranker
+                .rerank(&article.headline, candidates, RERANK_TOP_N, repo)
+                .await?;
+        }
+        let best_score = candidates
+            .iter()
+            .map(|c| c.relevance())
+            .max_by(|a, b| a.total_cmp(b));
+        let candidates = floor.apply(candidates);
+        if candidates.is_empty() {
+            repo.insert_unmatched(&article.uri, best_score).await.ok();
+        }
+        for candidate in diversify(candidates, NUM_COMBOS, diversity) {
+            repo.insert_combo(
+                &article.uri,
+                &candidate.info.uri,
+                candidate.score,
+                candidate.rerank_score,
             )
-            .bind(&article.uri)
-            .bind(&info.uri)
-            .bind(score)
-            .execute(pg)
-            .await
-            .ok();
+            .await?;
         }
-        article.upsert(pg).await.ok();
-    }
-    Ok(())
-}
-
-/// Truncate and remake the current table
-async fn remake_current(
-    current_articles: &Vec<ContemporaryArticle>,
-    pg: &Pool<Postgres>,
-) -> anyhow::Result<()> {
-    sqlx::query(
-        r#"
-        TRUNCATE TABLE current
-        "#,
-    )
-    .execute(pg)
-    .await?;
-    for (ix, article) in current_articles.iter().enumerate() {
-        sqlx::query(
-            r#"
-            INSERT INTO current (uri, rank)
-            VALUES ($1, $2)
-            "#,
-        )
-        .bind(&article.uri)
-        .bind(ix as i32)
-        .execute(pg)
-        .await?;
+        repo.upsert_contemporary(article).await.ok();
     }
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let api_key = env::var("NYT_API_KEY").unwrap();
-    let pool = get_pg_pool(2).await?;
+    let mut sources: Vec<Box<dyn NewsSource>> = vec![];
+    if USE_NYT {
+        sources.push(Box::new(NytSource::from_env()?));
+    }
+    for (name, url) in FEEDS {
+        sources.push(Box::new(FeedSource::new(name, url)));
+    }
+    let repo = get_repository(2).await?;
+
+    let embedder: Arc<dyn Embedder> = Arc::new(CachedEmbedder::try_new(
+        Box::new(FastEmbedder::try_new(BED_MODEL)?),
+        Path::new(BED_CACHE_DIR),
+    )?);
+    let reranker = match RERANK_MODEL {
+        Some(model) => Some(Reranker::try_new(model)?),
+        None => None,
+    };
+
+    let junk = JunkFilter::try_new(JUNK_RULES)?;
 
-    let current_articles = get_current_homepage(&api_key).await?;
-    update_combos(&current_articles, &pool).await?;
-    remake_current(&current_articles, &pool).await?;
+    // One source being down shouldn't stop the rest from getting combos
+    let mut current_articles = vec![];
+    let mut all_fetched = true;
+    for source in sources.iter() {
+        match source.latest().await {
+            Ok(articles) => {
+                println!("{}: {} articles", source.name(), articles.len());
+                current_articles.extend(articles);
+            }
+            Err(e) => {
+                println!("Couldn't get the latest from {}: {e}", source.name());
+                all_fetched = false;
+            }
+        }
+    }
+    update_combos(
+        &current_articles,
+        repo.as_ref(),
+        embedder,
+        DIVERSITY,
+        reranker.as_ref(),
+        SCORE_FLOOR,
+        &junk,
+    )
+    .await?;
+    junk.report();
+    // ...but a partial list would knock the missing source's articles off the front page
+    if all_fetched {
+        let current_uris = current_articles
+            .iter()
+            .map(|article| article.uri.clone())
+            .collect::<Vec<_>>();
+        repo.replace_current(&current_uris).await?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,53 @@
+use axum::{routing::get, Router};
+use cyclicism::{feed::FeedSource, source::NewsSource};
+use tokio::net::TcpListener;
+
+const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Test feed</title>
+    <link>https://example.com</link>
+    <description>Test feed</description>
+    <item>
+      <title>Dated story</title>
+      <link>https://example.com/dated</link>
+      <description>&lt;p&gt;Has a date&lt;/p&gt;</description>
+      <pubDate>Tue, 07 May 2024 10:00:00 -0400</pubDate>
+      <category>World</category>
+    </item>
+    <item>
+      <title>Undated story</title>
+      <link>https://example.com/undated</link>
+      <description>No date at all</description>
+    </item>
+    <item>
+      <title></title>
+      <link>https://example.com/untitled</link>
+      <pubDate>Tue, 07 May 2024 11:00:00 -0400</pubDate>
+    </item>
+  </channel>
+</rss>"#;
+
+/// Serves `RSS` on a free port and returns its url
+async fn serve_feed() -> anyhow::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/rss.xml", listener.local_addr()?);
+    let app = Router::new().route("/rss.xml", get(|| async { RSS }));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok(url)
+}
+
+#[tokio::test]
+async fn only_dated_titled_entries_come_through() -> anyhow::Result<()> {
+    let source = FeedSource::new("test", &serve_feed().await?);
+    let articles = source.latest().await?;
+    assert_eq!(articles.len(), 1);
+    let article = &articles[0];
+    assert_eq!(article.source, "test");
+    assert_eq!(article.uri, "https://example.com/dated");
+    assert_eq!(article.headline, "Dated story");
+    assert_eq!(article.summary, "Has a date");
+    assert_eq!(article.date_parts(), (2024, 5, 7));
+    assert_eq!(article.section, "World");
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use cyclicism::{
+    embed::Embedder,
+    flat::FlatStore,
+    mydrant::{BedSource, CommonInfo, DetailedEmbedding},
+    nyt::uri_to_uuid,
+    store::VectorStore,
+};
+use qdrant_client::qdrant::Distance;
+
+const DIM: u64 = 4;
+
+/// `FlatStore` only needs the dim and model id, nothing gets embedded
+struct FakeEmbedder;
+impl Embedder for FakeEmbedder {
+    fn embed(&self, _texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Err(anyhow::anyhow!("FakeEmbedder can't embed"))
+    }
+
+    fn dim(&self) -> u64 {
+        DIM
+    }
+
+    fn model_id(&self) -> String {
+        "Fake".to_string()
+    }
+}
+
+fn point(n: u32) -> DetailedEmbedding {
+    let uri = format!("nyt://article/{n}");
+    DetailedEmbedding {
+        uuid: uri_to_uuid(&uri),
+        bed: (0..DIM).map(|d| (n + d as u32) as f32).collect(),
+        info: CommonInfo {
+            uri,
+            year: 1990,
+            month: 1,
+            day: n % 28 + 1,
+            print_section: None,
+            document_type: "article".to_string(),
+            news_desk: "Foreign".to_string(),
+            type_of_material: "News".to_string(),
+        },
+    }
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("cyclicism_tests");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}_{}.flat", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    path
+}
+
+fn open(path: &Path) -> anyhow::Result<FlatStore> {
+    FlatStore::open(
+        path,
+        BedSource::HeadlineMain,
+        &FakeEmbedder,
+        Distance::Cosine,
+    )
+}
+
+#[tokio::test]
+async fn reopens_with_every_point() -> anyhow::Result<()> {
+    let path = scratch_path("reopen");
+    open(&path)?.upsert((0..5).map(point).collect()).await?;
+    let store = open(&path)?;
+    assert_eq!(store.len(), 5);
+    let best = store.top_k(point(3).bed, 1).await?;
+    assert_eq!(best[0].0.uri, "nyt://article/3");
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn drops_a_torn_last_record() -> anyhow::Result<()> {
+    let path = scratch_path("torn");
+    let store = open(&path)?;
+    store.upsert(vec![point(0), point(1)]).await?;
+    let complete_len = std::fs::metadata(&path)?.len() as usize;
+    store.upsert(vec![point(2)]).await?;
+    drop(store);
+    let full = std::fs::read(&path)?;
+
+    // Inside the length prefix, right after it, inside the json, inside the bed
+    let cuts = [
+        complete_len + 1,
+        complete_len + 3,
+        complete_len + 4,
+        complete_len + 10,
+        full.len() - 1,
+    ];
+    for cut in cuts {
+        std::fs::write(&path, &full[..cut])?;
+        let store = open(&path)?;
+        assert_eq!(store.len(), 2, "cut at {cut}");
+        assert_eq!(std::fs::metadata(&path)?.len() as usize, complete_len);
+
+        // Appends have to land after the last complete record
+        store.upsert(vec![point(3)]).await?;
+        drop(store);
+        assert_eq!(open(&path)?.len(), 3, "cut at {cut}");
+    }
+
+    // Exactly on a record boundary isn't torn
+    std::fs::write(&path, &full[..complete_len])?;
+    assert_eq!(open(&path)?.len(), 2);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejects_a_bad_batch_whole() -> anyhow::Result<()> {
+    let path = scratch_path("bad_batch");
+    let store = open(&path)?;
+    store.upsert(vec![point(0)]).await?;
+    let len = std::fs::metadata(&path)?.len();
+
+    let mut short = point(2);
+    short.bed.pop();
+    assert!(store.upsert(vec![point(1), short]).await.is_err());
+    let mut misfiled = point(2);
+    misfiled.uuid = point(3).uuid;
+    assert!(store.upsert(vec![point(1), misfiled]).await.is_err());
+
+    // Nothing from either batch, in memory or on disk
+    assert_eq!(store.len(), 1);
+    store.upsert(vec![point(4)]).await?;
+    drop(store);
+    assert!(std::fs::metadata(&path)?.len() > len);
+    let store = open(&path)?;
+    assert_eq!(store.len(), 2);
+    let best = store.top_k(point(1).bed, 2).await?;
+    assert!(best.iter().all(|(info, _)| info.uri != "nyt://article/1"));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
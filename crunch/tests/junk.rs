@@ -0,0 +1,41 @@
+use chrono::DateTime;
+use cyclicism::{
+    junk::{JunkFilter, JunkReason, JunkRules},
+    source::NewsArticle,
+};
+
+fn news(headline: &str, type_of_material: &str) -> NewsArticle {
+    NewsArticle {
+        source: "nyt".to_string(),
+        uri: "nyt://article/now".to_string(),
+        url: "https://www.nytimes.com/2024/05/07/world/now.html".to_string(),
+        headline: headline.to_string(),
+        summary: String::new(),
+        published_at: DateTime::parse_from_rfc3339("2024-05-07T10:00:00-04:00").unwrap(),
+        section: "world".to_string(),
+        subsection: "europe".to_string(),
+        kind: "Article".to_string(),
+        kicker: String::new(),
+        type_of_material: type_of_material.to_string(),
+        tags: vec![],
+        image: None,
+    }
+}
+
+#[test]
+fn contemporary_articles_get_the_type_of_material_rules() -> anyhow::Result<()> {
+    let junk = JunkFilter::try_new(JunkRules::DEFAULT)?;
+    let headline = "Parliament Votes on the New Budget";
+    assert_eq!(junk.check_news(&news(headline, "News")), None);
+    assert_eq!(
+        junk.check_news(&news(headline, "Correction")),
+        Some(JunkReason::TypeOfMaterial("Correction".to_string()))
+    );
+    // Feeds never say, so it's down to the headline
+    assert_eq!(junk.check_news(&news(headline, "")), None);
+    assert_eq!(
+        junk.check_news(&news("Corrections: May 7", "")),
+        Some(JunkReason::Headline(r"^corrections?\b".to_string()))
+    );
+    Ok(())
+}
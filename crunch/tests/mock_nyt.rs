@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use cyclicism::{
+    archive::{ArchiveClient, MonthStatus},
+    compress::Compression,
+    manifest::Manifest,
+    mock::{serve, Faults},
+    nyt::get_current_homepage,
+};
+use tokio::net::TcpListener;
+
+/// Never scraped, so `handle_month` always asks the mock and every failure path stays off disk
+const UNSCRAPED: NaiveDate = NaiveDate::from_ymd_opt(2999, 1, 1).unwrap();
+
+/// Starts a mock on a free port and returns its base url
+async fn mock(faults: Faults) -> anyhow::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(serve(listener, faults));
+    Ok(base_url)
+}
+
+async fn month_status(faults: Faults, api_key: &str) -> anyhow::Result<MonthStatus> {
+    let client = ArchiveClient::new(&mock(faults).await?, api_key);
+    let mut manifest = Manifest::default();
+    let status = client
+        .handle_month(UNSCRAPED, Compression::Plain, &mut manifest)
+        .await;
+    assert!(manifest.months.is_empty());
+    Ok(status)
+}
+
+#[tokio::test]
+async fn archive_retries_a_429_after_what_it_asked_for() -> anyhow::Result<()> {
+    let faults = Faults {
+        rate_limit_every: Some(1),
+        ..Default::default()
+    };
+    match month_status(faults, "key").await? {
+        MonthStatus::Retry { after, .. } => assert_eq!(after, Some(Duration::from_secs(1))),
+        other => panic!("expected a retry, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn archive_retries_a_503() -> anyhow::Result<()> {
+    let faults = Faults {
+        server_error_every: Some(1),
+        ..Default::default()
+    };
+    match month_status(faults, "key").await? {
+        MonthStatus::Retry { reason, after } => {
+            assert!(reason.contains("503"), "{reason}");
+            assert_eq!(after, None);
+        }
+        other => panic!("expected a retry, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn archive_retries_a_malformed_body() -> anyhow::Result<()> {
+    let faults = Faults {
+        malformed_every: Some(1),
+        ..Default::default()
+    };
+    match month_status(faults, "key").await? {
+        MonthStatus::Retry { reason, .. } => assert!(reason.contains("body"), "{reason}"),
+        other => panic!("expected a retry, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn archive_gives_up_on_a_bad_key() -> anyhow::Result<()> {
+    match month_status(Faults::default(), "bad").await? {
+        MonthStatus::Fatal(reason) => assert!(reason.contains("401"), "{reason}"),
+        other => panic!("expected it to give up, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn homepage_comes_back_whole() -> anyhow::Result<()> {
+    let base_url = mock(Faults::default()).await?;
+    assert!(!get_current_homepage(&base_url, "key").await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn homepage_fails_on_every_fault() -> anyhow::Result<()> {
+    let faulty = [
+        Faults {
+            rate_limit_every: Some(1),
+            ..Default::default()
+        },
+        Faults {
+            server_error_every: Some(1),
+            ..Default::default()
+        },
+        Faults {
+            malformed_every: Some(1),
+            ..Default::default()
+        },
+    ];
+    for faults in faulty {
+        let what = format!("{faults:?}");
+        let base_url = mock(faults).await?;
+        assert!(
+            get_current_homepage(&base_url, "key").await.is_err(),
+            "{what}"
+        );
+    }
+    let base_url = mock(Faults::default()).await?;
+    assert!(get_current_homepage(&base_url, "bad").await.is_err());
+    Ok(())
+}
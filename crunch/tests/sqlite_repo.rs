@@ -0,0 +1,213 @@
+use chrono::DateTime;
+use cyclicism::{
+    nyt::ScrapedArticle,
+    repo::Repository,
+    source::{NewsArticle, NewsImage},
+    sqlite::SqliteRepository,
+};
+
+/// Every connection to `sqlite::memory:` gets its own database, so there can only be one
+async fn memory_repo() -> anyhow::Result<SqliteRepository> {
+    let repo = SqliteRepository::connect("sqlite::memory:", 1).await?;
+    repo.apply_migrations().await?;
+    Ok(repo)
+}
+
+fn scraped(n: u32) -> ScrapedArticle {
+    serde_json::from_value(serde_json::json!({
+        "web_url": format!("https://www.nytimes.com/1990/01/0{n}/world/article-{n}.html"),
+        "snippet": format!("Snippet {n}"),
+        "print_page": "1",
+        "print_section": "A",
+        "source": "The New York Times",
+        "multimedia": [{
+            "rank": 0,
+            "subtype": "xlarge",
+            "caption": null,
+            "credit": null,
+            "type": "image",
+            "url": format!("images/1990/01/0{n}/article-{n}.jpg"),
+            "height": 400,
+            "width": 600,
+            "legacy": { "xlarge": null, "xlargewidth": null, "xlargeheight": null },
+            "crop_name": "articleLarge"
+        }],
+        "headline": {
+            "main": format!("Headline {n}"),
+            "kicker": null,
+            "content_kicker": null,
+            "print_headline": format!("Headline {n}"),
+            "name": null,
+            "seo": null,
+            "sub": null
+        },
+        "keywords": [{ "name": "glocations", "value": "Berlin", "rank": 1, "major": "N" }],
+        "pub_date": format!("1990-01-0{n}T05:00:00+0000"),
+        "document_type": "article",
+        "news_desk": "Foreign Desk",
+        "section_name": "World",
+        "byline": { "original": "By Someone", "person": [], "organization": null },
+        "type_of_material": "News",
+        "uri": format!("nyt://article/{n}")
+    }))
+    .unwrap()
+}
+
+fn news(n: u32) -> NewsArticle {
+    NewsArticle {
+        source: "nyt".to_string(),
+        uri: format!("nyt://article/now-{n}"),
+        url: format!("https://www.nytimes.com/2024/05/0{n}/world/now-{n}.html"),
+        headline: format!("Now {n}"),
+        summary: format!("Summary {n}"),
+        published_at: DateTime::parse_from_rfc3339(&format!("2024-05-0{n}T10:00:00-04:00"))
+            .unwrap(),
+        section: "world".to_string(),
+        subsection: "europe".to_string(),
+        kind: "Article".to_string(),
+        kicker: "Kicker".to_string(),
+        type_of_material: "News".to_string(),
+        tags: vec!["Berlin".to_string()],
+        image: Some(NewsImage {
+            url: format!("https://static01.nyt.com/now-{n}.jpg"),
+            caption: Some(format!("Caption {n}")),
+            format: Some("Super Jumbo".to_string()),
+            type_: "image".to_string(),
+            subtype: Some("photo".to_string()),
+        }),
+    }
+}
+
+#[tokio::test]
+async fn archive_articles_round_trip() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    repo.upsert_scraped(&scraped(1)).await?;
+    // Upserting again shouldn't trip any unique constraints
+    repo.upsert_scraped(&scraped(1)).await?;
+
+    let article = repo.archive_article("nyt://article/1").await?;
+    assert_eq!(article.headline_main, "Headline 1");
+    assert_eq!(article.snippet, "Snippet 1");
+    assert_eq!((article.year, article.month, article.day), (1990, 1, 1));
+    assert_eq!(article.news_desk, "Foreign Desk");
+    assert!(article.image.is_some());
+    assert!(repo.archive_article("nyt://article/2").await.is_err());
+    assert!(repo.has_archive_article("nyt://article/1").await?);
+    assert!(!repo.has_archive_article("nyt://article/2").await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn contemporary_articles_round_trip() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    repo.upsert_contemporary(&news(1)).await?;
+    repo.upsert_contemporary(&news(1)).await?;
+    repo.upsert_contemporary(&news(2)).await?;
+
+    let article = repo.contemporary_article("nyt://article/now-1").await?;
+    assert_eq!(article.headline_main, "Now 1");
+    assert_eq!(article.snippet, "Summary 1");
+    assert_eq!(article.type_of_material, "europe");
+    // The article's own timezone, not UTC
+    assert_eq!((article.year, article.month, article.day), (2024, 5, 1));
+    assert_eq!(
+        article.image.map(|image| image.caption),
+        Some(Some("Caption 1".to_string()))
+    );
+    assert_eq!(
+        repo.contemporary_uris_on(2024, 5, 2).await?,
+        vec!["nyt://article/now-2".to_string()]
+    );
+
+    let mut sample = repo.sample_contemporary_titles(5).await?;
+    sample.sort();
+    assert_eq!(sample, vec!["Now 1", "Now 2"]);
+    assert_eq!(repo.sample_contemporary_titles(1).await?.len(), 1);
+
+    let pending = repo.unpromoted_contemporary().await?;
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].source, "nyt");
+    repo.mark_promoted("nyt://article/now-1", false).await?;
+    assert_eq!(repo.unpromoted_contemporary().await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn duplicates_point_at_their_canonical() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    repo.upsert_scraped(&scraped(1)).await?;
+    repo.upsert_scraped(&scraped(2)).await?;
+    let uris = vec!["nyt://article/1".to_string(), "nyt://article/3".to_string()];
+    let headlines = repo.archive_headlines(&uris).await?;
+    assert_eq!(headlines.len(), 1);
+    assert_eq!(headlines["nyt://article/1"], "Headline 1");
+
+    let mapping = vec![("nyt://article/2".to_string(), "nyt://article/1".to_string())];
+    repo.store_canonical_uris(&mapping).await?;
+    repo.store_canonical_uris(&mapping).await?;
+    let canonical = repo
+        .canonical_uris(&["nyt://article/1".to_string(), "nyt://article/2".to_string()])
+        .await?;
+    assert_eq!(canonical.len(), 1);
+    assert_eq!(canonical["nyt://article/2"], "nyt://article/1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn combos_come_back_best_first() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    let now = "nyt://article/now-1";
+    repo.insert_combo(now, "nyt://article/1", 0.9, None).await?;
+    repo.insert_combo(now, "nyt://article/2", 0.8, Some(2.0))
+        .await?;
+    repo.insert_combo(now, "nyt://article/3", 0.95, Some(-1.0))
+        .await?;
+    repo.insert_combo(now, "nyt://article/4", 0.7, None).await?;
+    // Re-running the updater rescores rather than failing
+    repo.insert_combo(now, "nyt://article/4", 0.99, None)
+        .await?;
+    // Reranked ones first by rerank score, then the rest by qdrant score
+    assert_eq!(
+        repo.combo_uris(now).await?,
+        vec![
+            "nyt://article/2",
+            "nyt://article/3",
+            "nyt://article/4",
+            "nyt://article/1"
+        ]
+    );
+    assert!(repo.is_matched(now).await?);
+    assert!(!repo.is_unmatched(now).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unmatched_counts_as_matched() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    let now = "nyt://article/now-1";
+    assert!(!repo.is_matched(now).await?);
+    assert!(!repo.is_unmatched(now).await?);
+
+    repo.insert_unmatched(now, Some(0.4)).await?;
+    // Checking again later just updates it
+    repo.insert_unmatched(now, None).await?;
+    assert!(repo.is_matched(now).await?);
+    assert!(repo.is_unmatched(now).await?);
+    assert!(repo.combo_uris(now).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn current_is_replaced_in_order() -> anyhow::Result<()> {
+    let repo = memory_repo().await?;
+    assert!(repo.current_uris().await?.is_empty());
+
+    let first = vec!["b".to_string(), "a".to_string(), "c".to_string()];
+    repo.replace_current(&first).await?;
+    assert_eq!(repo.current_uris().await?, first);
+
+    let second = vec!["c".to_string(), "d".to_string()];
+    repo.replace_current(&second).await?;
+    assert_eq!(repo.current_uris().await?, second);
+    Ok(())
+}